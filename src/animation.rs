@@ -1,10 +1,14 @@
-use raylib::prelude::{Texture2D, Rectangle};
-use raylib::{RaylibHandle, RaylibThread};
+use raylib::prelude::{Texture2D, Rectangle, Vector2};
+use crate::state_machine::StateGraph;
 use std::sync::Arc;
 use std::collections::HashMap;
+use std::ops::Range;
+use anyhow::bail;
+use serde::Deserialize;
 
 /// High-level facing for sprites.
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Facing {
     Left,
     Right,
@@ -13,7 +17,8 @@ pub enum Facing {
 }
 
 /// Pose/state that affects which animation to player.
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Pose {
     Sitting,
     Walking,
@@ -23,124 +28,768 @@ pub enum Pose {
 }
 
 /// Emotion or "flavor" of the sprite (changes face, etc)
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Emotion {
     Neutral,
     Happy,
     Sad,
 }
 
+/// Order the frames of a clip are played in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackMode {
+    Forward,
+    Reverse,
+    PingPong, // forward then back again, without repeating the end frames
+}
+
+/// Named event attached to a frame, e.g. "bark" or "footstep". Fires every
+/// time the player enters that frame.
+#[derive(Clone, Debug)]
+pub struct FrameEvent {
+    pub frame: usize,
+    pub name: String,
+}
+
+/// What the animation code needs from a texture: cloning it cheaply and
+/// telling two handles apart. The timing logic never looks inside, so tests
+/// can use a stand-in and run without a window.
+pub trait TextureHandle: Clone {
+    fn same_texture(&self, other: &Self) -> bool;
+}
+
+/// The real thing: a loaded sheet shared between every clip cut from it
+pub type Sheet = Arc<Texture2D>;
+
+impl TextureHandle for Sheet {
+    fn same_texture(&self, other: &Self) -> bool {
+        Arc::ptr_eq(self, other)
+    }
+}
+
 /// Descriptor: texture = frames + timing. Shared across instances.
 #[derive(Clone)]
-pub struct AnimationDescriptor {
-    pub texture: Arc<Texture2D>,
+pub struct AnimationDescriptor<T = Sheet> {
+    pub texture: T,
     pub frames: Vec<Rectangle>, // source rects in texture
-    pub frame_duration_secs: f32,
+    pub frame_durations: Vec<f32>, // seconds, one per frame
     pub looped: bool,
+    pub mode: PlaybackMode,
+    pub events: Vec<FrameEvent>,
+    // false for art that must not be flipped (text, asymmetric markings, ...)
+    pub mirrorable: bool,
+    // point in the frame that stays put when the sprite is mirrored.
+    // None mirrors in place, inside the same destination rect
+    pub pivot: Option<Vector2>,
 }
 
-impl AnimationDescriptor {
+impl<T: TextureHandle> AnimationDescriptor<T> {
+    /// Every frame shown for the same amount of time, played forward
+    pub fn new(texture: T, frames: Vec<Rectangle>, frame_duration_secs: f32, looped: bool) -> Self {
+        let frame_durations = vec![frame_duration_secs; frames.len()];
+        Self { texture, frames, frame_durations, looped, mode: PlaybackMode::Forward, events: Vec::new(), mirrorable: true, pivot: None }
+    }
+
+    /// Per-frame timings, e.g. the 600ms / 150ms door frames in the TMX tilesets.
+    /// Missing entries repeat the last given duration.
+    pub fn with_frame_durations(mut self, durations: Vec<f32>) -> Self {
+        let fallback = durations.last().copied().unwrap_or(0.0);
+        self.frame_durations = (0..self.frames.len())
+            .map(|i| durations.get(i).copied().unwrap_or(fallback))
+            .collect();
+        self
+    }
+
+    pub fn with_mode(mut self, mode: PlaybackMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_mirrorable(mut self, mirrorable: bool) -> Self {
+        self.mirrorable = mirrorable;
+        self
+    }
+
+    pub fn with_pivot(mut self, pivot: Vector2) -> Self {
+        self.pivot = Some(pivot);
+        self
+    }
+
+    pub fn with_event(mut self, frame: usize, name: &str) -> Self {
+        self.add_event(frame, name);
+        self
+    }
+
+    pub fn add_event(&mut self, frame: usize, name: &str) {
+        self.events.push(FrameEvent { frame, name: name.to_string() });
+    }
+
+    fn events_on(&self, frame: usize) -> impl Iterator<Item = &FrameEvent> {
+        self.events.iter().filter(move |e| e.frame == frame)
+    }
+
     pub fn len(&self) -> usize { self.frames.len() }
+
+    /// Same frames of the same texture, i.e. clones of one clip
+    pub fn same_clip(&self, other: &AnimationDescriptor<T>) -> bool {
+        self.texture.same_texture(&other.texture)
+            && self.frames.len() == other.frames.len()
+            && self.frames.iter().zip(&other.frames).all(|(a, b)| {
+                a.x == b.x && a.y == b.y && a.width == b.width && a.height == b.height
+            })
+    }
+
+    pub fn frame_duration(&self, frame: usize) -> f32 {
+        self.frame_durations.get(frame).copied().unwrap_or(0.0)
+    }
+
+    /// Length of one pass through the frames, ignoring mode
+    pub fn total_duration(&self) -> f32 {
+        self.frame_durations.iter().sum()
+    }
+
+    fn first_frame(&self) -> usize {
+        match self.mode {
+            PlaybackMode::Reverse => self.len().saturating_sub(1),
+            _ => 0,
+        }
+    }
 }
 
 /// Per-instance player: current frame index + elapsed time
-pub struct AnimationPlayer {
-    pub descriptor: AnimationDescriptor,
+pub struct AnimationPlayer<T = Sheet> {
+    pub descriptor: AnimationDescriptor<T>,
     pub current_frame: usize,
     pub elapsed: f32, // seconds into current frame
     pub playing: bool,
+    pub playback_rate: f32, // 1.0 = as authored, 2.0 = twice as fast
+    pub mirrored: bool, // descriptor art faces the other way (see AnimationBank::resolve)
+    ping_pong_forward: bool,
+    // the first frame hasn't had its events reported yet
+    entered_first_frame: bool,
 }
 
-impl AnimationPlayer {
-    pub fn new(desc: AnimationDescriptor) -> Self {
-        Self { descriptor: desc, current_frame: 0, elapsed: 0.0, playing: true }
+impl<T: TextureHandle> AnimationPlayer<T> {
+    pub fn new(desc: AnimationDescriptor<T>) -> Self {
+        let first = desc.first_frame();
+        Self {
+            descriptor: desc,
+            current_frame: first,
+            elapsed: 0.0,
+            playing: true,
+            playback_rate: 1.0,
+            mirrored: false,
+            ping_pong_forward: true,
+            entered_first_frame: false,
+        }
+    }
+
+    /// Advance animation by dt seconds. Returns the names of the frame events
+    /// fired along the way, in order, including frames skipped over by a big dt.
+    pub fn update(&mut self, dt: f32) -> Vec<String> {
+        let mut fired = Vec::new();
+        if !self.playing { return fired; }
+
+        if !self.entered_first_frame {
+            self.entered_first_frame = true;
+            self.collect_events(&mut fired);
+        }
+
+        let len = self.descriptor.len();
+        if len == 0 {
+            self.stop();
+            return fired;
+        }
+        if len == 1 && self.descriptor.looped { return fired; }
+
+        // a clip with no duration at all would spin forever below. if it's
+        // a one-shot it's simply over straight away
+        if self.descriptor.total_duration() <= 0.0 {
+            if !self.descriptor.looped {
+                self.stop();
+            }
+            return fired;
+        }
+        if self.playback_rate <= 0.0 { return fired; }
+
+        self.elapsed += dt * self.playback_rate;
+        while self.elapsed >= self.descriptor.frame_duration(self.current_frame) {
+            self.elapsed -= self.descriptor.frame_duration(self.current_frame);
+            if !self.step_frame() {
+                break;
+            }
+            self.collect_events(&mut fired);
+        }
+
+        fired
+    }
+
+    fn collect_events(&self, fired: &mut Vec<String>) {
+        fired.extend(self.descriptor.events_on(self.current_frame).map(|e| e.name.clone()));
     }
 
-    /// Advance animation by dt seconds
-    pub fn update(&mut self, dt: f32) {
-        if !self.playing || self.descriptor.len() <= 1 { return; }
-        self.elapsed += dt;
-        while self.elapsed >= self.descriptor.frame_duration_secs {
-            self.elapsed -= self.descriptor.frame_duration_secs;
-            self.current_frame += 1;
-            if self.current_frame >= self.descriptor.len() {
-                if self.descriptor.looped {
+    /// Moves to the next frame for the clip's mode. Returns false once a
+    /// non-looped clip has reached its end and stopped.
+    fn step_frame(&mut self) -> bool {
+        let last = self.descriptor.len() - 1;
+        let looped = self.descriptor.looped;
+
+        match self.descriptor.mode {
+            PlaybackMode::Forward => {
+                if self.current_frame < last {
+                    self.current_frame += 1;
+                } else if looped {
                     self.current_frame = 0;
                 } else {
-                    self.current_frame = self.descriptor.len().saturating_sub(1);
-                    self.playing = false;
+                    return self.stop();
                 }
-            }
+            },
+            PlaybackMode::Reverse => {
+                if self.current_frame > 0 {
+                    self.current_frame -= 1;
+                } else if looped {
+                    self.current_frame = last;
+                } else {
+                    return self.stop();
+                }
+            },
+            PlaybackMode::PingPong => {
+                if self.ping_pong_forward {
+                    if self.current_frame < last {
+                        self.current_frame += 1;
+                    } else if last > 0 {
+                        self.ping_pong_forward = false;
+                        self.current_frame -= 1;
+                    } else {
+                        // single frame, nowhere to bounce to
+                        return self.stop();
+                    }
+                } else if self.current_frame > 0 {
+                    self.current_frame -= 1;
+                } else if looped {
+                    self.ping_pong_forward = true;
+                    self.current_frame = 1;
+                } else {
+                    return self.stop();
+                }
+            },
         }
+
+        true
+    }
+
+    fn stop(&mut self) -> bool {
+        self.playing = false;
+        self.elapsed = 0.0;
+        false
     }
 
     pub fn reset(&mut self) {
-        self.current_frame = 0;
+        self.current_frame = self.descriptor.first_frame();
         self.elapsed = 0.0;
         self.playing = true;
+        self.ping_pong_forward = true;
+        self.entered_first_frame = false;
+    }
+
+    pub fn set_playback_rate(&mut self, rate: f32) {
+        self.playback_rate = rate.max(0.0);
     }
 
     pub fn current_frame_rect(&self) -> Rectangle {
         self.descriptor.frames[self.current_frame]
     }
 
-    pub fn texture(&self) -> &Texture2D {
+    /// Source rect to draw with. Mirrored frames get a negative width, which
+    /// makes draw_texture_pro flip them horizontally.
+    pub fn source_rect(&self) -> Rectangle {
+        let mut src = self.current_frame_rect();
+        if self.mirrored {
+            src.width = -src.width;
+        }
+        src
+    }
+
+    /// Origin to pass to draw_texture_pro alongside a destination rect at the
+    /// entity position. Keeps the pivot on the same spot whichever way we face.
+    pub fn origin(&self) -> Vector2 {
+        match self.descriptor.pivot {
+            Some(pivot) if self.mirrored => {
+                let width = self.current_frame_rect().width;
+                Vector2::new(width - pivot.x, pivot.y)
+            },
+            Some(pivot) => pivot,
+            None => Vector2::new(0.0, 0.0),
+        }
+    }
+
+    pub fn texture(&self) -> &T {
         &self.descriptor.texture
     }
 }
 
 
-#[derive(Hash, Eq, PartialEq, Debug)]
+/// Layout of a sprite sheet where frames sit on a regular grid, like the
+/// images/animals sheets and the TMX tilesets. All measurements in pixels.
+/// Frames are cut row by row, left to right.
+#[derive(Clone, Debug)]
+pub struct SheetGrid {
+    pub frame_width: i32,
+    pub frame_height: i32,
+    pub margin: i32,  // empty border around the whole sheet
+    pub spacing: i32, // gap between neighbouring frames
+    pub rows: Option<Range<usize>>,    // None = every row on the sheet
+    pub columns: Option<Range<usize>>, // None = every column on the sheet
+}
+
+impl SheetGrid {
+    pub fn new(frame_width: i32, frame_height: i32) -> Self {
+        Self { frame_width, frame_height, margin: 0, spacing: 0, rows: None, columns: None }
+    }
+
+    pub fn with_margin(mut self, margin: i32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_spacing(mut self, spacing: i32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn with_rows(mut self, rows: Range<usize>) -> Self {
+        self.rows = Some(rows);
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Range<usize>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// How many whole frames fit along `extent` pixels of sheet
+    fn cells_along(&self, extent: i32, frame: i32) -> usize {
+        let usable = extent - 2 * self.margin + self.spacing;
+        if frame <= 0 || usable <= 0 {
+            return 0;
+        }
+        (usable / (frame + self.spacing)) as usize
+    }
+
+    pub fn column_count(&self, sheet_width: i32) -> usize {
+        self.cells_along(sheet_width, self.frame_width)
+    }
+
+    pub fn row_count(&self, sheet_height: i32) -> usize {
+        self.cells_along(sheet_height, self.frame_height)
+    }
+
+    /// Source rect of the frame at (row, column)
+    pub fn cell(&self, row: usize, column: usize) -> Rectangle {
+        Rectangle {
+            x: (self.margin + column as i32 * (self.frame_width + self.spacing)) as f32,
+            y: (self.margin + row as i32 * (self.frame_height + self.spacing)) as f32,
+            width: self.frame_width as f32,
+            height: self.frame_height as f32,
+        }
+    }
+
+    /// Cuts the selected rows and columns out of a sheet of the given size.
+    /// Fails rather than handing back rects that hang off the sheet.
+    pub fn frames(&self, sheet_width: i32, sheet_height: i32) -> anyhow::Result<Vec<Rectangle>> {
+        let total_columns = self.column_count(sheet_width);
+        let total_rows = self.row_count(sheet_height);
+
+        let columns = self.columns.clone().unwrap_or(0..total_columns);
+        let rows = self.rows.clone().unwrap_or(0..total_rows);
+
+        if columns.end > total_columns || rows.end > total_rows {
+            bail!(
+                "grid {}x{} (margin {}, spacing {}) wants rows {:?} columns {:?}, but a {}x{} sheet only has {} rows and {} columns",
+                self.frame_width, self.frame_height, self.margin, self.spacing,
+                rows, columns, sheet_width, sheet_height, total_rows, total_columns
+            );
+        }
+        if columns.is_empty() || rows.is_empty() {
+            bail!("grid selects no frames (rows {:?}, columns {:?})", rows, columns);
+        }
+
+        let mut rects = Vec::with_capacity(rows.len() * columns.len());
+        for row in rows {
+            for column in columns.clone() {
+                rects.push(self.cell(row, column));
+            }
+        }
+
+        Ok(rects)
+    }
+}
+
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
 pub struct AnimationKey {
     pub pose: Pose,
     pub emotion: Emotion,
     pub facing: Facing,
 }
 
+/// What `AnimationBank::resolve` found for a key. `mirrored` means the
+/// descriptor faces the other way and has to be flipped horizontally.
+pub struct ResolvedAnimation<'a> {
+    pub descriptor: &'a AnimationDescriptor,
+    pub mirrored: bool,
+}
+
 pub struct AnimationBank {
     pub map: HashMap<AnimationKey, AnimationDescriptor>,
+    // one-off animations looked up by name ("bark", "attack", ...) rather than by pose
+    pub clips: HashMap<String, AnimationDescriptor>,
+    // how to get from one pose to another, see state_machine.rs
+    pub graph: StateGraph,
 }
 
 impl AnimationBank {
-    pub fn new() -> Self { Self { map: HashMap::new() } }
+    pub fn new() -> Self { Self { map: HashMap::new(), clips: HashMap::new(), graph: StateGraph::default() } }
 
     pub fn get(&self, key: &AnimationKey) -> Option<&AnimationDescriptor> {
         return self.map.get(key)
     }
 
-    pub fn insert_strip(
-        &mut self,
-        rl: &mut RaylibHandle,
-        thread: &RaylibThread,
-        key: AnimationKey,
-        path: &str,
-        frames: usize,
-        frame_duration_secs: f32,
-        looped: bool,
-    ) -> anyhow::Result<()> {
-        let tex = rl.load_texture(thread, path)?;
-        let tex = Arc::new(tex);
-
-        let frame_w = (tex.width / frames as i32) as f32;
-        let frame_h = tex.height as f32;
-
-        let mut rects = Vec::with_capacity(frames);
-        for i in 0..frames {
-            rects.push(Rectangle {
-                x: (i as f32) * frame_w,
-                y: 0.0,
-                width: frame_w,
-                height: frame_h,
-            });
-        }
-
-        self.map.insert(key, AnimationDescriptor { 
-            texture: tex, 
-            frames: rects, 
-            frame_duration_secs, 
-            looped,
-        });
-
-        Ok(())
-    }
-}
\ No newline at end of file
+    pub fn clip(&self, name: &str) -> Option<&AnimationDescriptor> {
+        self.clips.get(name)
+    }
+
+    /// Finds the best animation for `key`, falling back when the sprite set
+    /// doesn't have an exact match:
+    ///   1. the exact key
+    ///   2. the same pose and facing with a Neutral emotion
+    ///   3. for Left, the Right animation mirrored (again trying the emotion, then Neutral)
+    ///   4. for Front/Back, the Right animation as is, since side-on is all most packs have
+    pub fn resolve(&self, key: &AnimationKey) -> Option<ResolvedAnimation<'_>> {
+        let with = |emotion: Emotion, facing: Facing| AnimationKey { pose: key.pose, emotion, facing };
+
+        let same_facing = self.map.get(key)
+            .or_else(|| self.map.get(&with(Emotion::Neutral, key.facing)));
+        if let Some(descriptor) = same_facing {
+            return Some(ResolvedAnimation { descriptor, mirrored: false });
+        }
+
+        let right = self.map.get(&with(key.emotion, Facing::Right))
+            .or_else(|| self.map.get(&with(Emotion::Neutral, Facing::Right)))?;
+
+        // clips that opted out of mirroring still beat showing nothing
+        let mirrored = key.facing == Facing::Left && right.mirrorable;
+        Some(ResolvedAnimation { descriptor: right, mirrored })
+    }
+
+    /// Insert a hand-built descriptor (custom timings, playback mode, ...)
+    pub fn insert(&mut self, key: AnimationKey, descriptor: AnimationDescriptor) {
+        self.map.insert(key, descriptor);
+    }
+
+    pub fn insert_clip(&mut self, name: &str, descriptor: AnimationDescriptor) {
+        self.clips.insert(name.to_string(), descriptor);
+    }
+
+    /// Use the named clip for a pose/emotion/facing key as well
+    pub fn bind(&mut self, key: AnimationKey, clip: &str) {
+        if let Some(descriptor) = self.clips.get(clip) {
+            self.map.insert(key, descriptor.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // stands in for a loaded sheet, so none of this needs a window
+    #[derive(Clone, Debug)]
+    pub(crate) struct FakeSheet(pub u32);
+
+    impl TextureHandle for FakeSheet {
+        fn same_texture(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    pub(crate) fn clip(frames: usize, frame_secs: f32, looped: bool) -> AnimationDescriptor<FakeSheet> {
+        let rects = (0..frames).map(|i| Rectangle::new(i as f32 * 16.0, 0.0, 16.0, 16.0)).collect();
+        AnimationDescriptor::new(FakeSheet(1), rects, frame_secs, looped)
+    }
+
+    fn frames_seen(player: &mut AnimationPlayer<FakeSheet>, ticks: usize, dt: f32) -> Vec<usize> {
+        (0..ticks).map(|_| { player.update(dt); player.current_frame }).collect()
+    }
+
+    #[test]
+    fn large_dt_skips_frames_and_wraps() {
+        let mut player = AnimationPlayer::new(clip(4, 0.1, true));
+        player.update(0.95);
+
+        // nine frame steps from frame 0 around a 4 frame loop
+        assert_eq!(player.current_frame, 1);
+        assert!((player.elapsed - 0.05).abs() < 1e-4);
+        assert!(player.playing);
+    }
+
+    #[test]
+    fn large_dt_reports_events_on_skipped_frames_in_order() {
+        let descriptor = clip(4, 0.1, true).with_event(1, "left").with_event(3, "right").with_event(0, "start");
+        let mut player = AnimationPlayer::new(descriptor);
+
+        assert_eq!(player.update(0.45), vec!["start", "left", "right", "start"]);
+        assert_eq!(player.current_frame, 0);
+    }
+
+    #[test]
+    fn first_frame_event_fires_on_first_update_only() {
+        let mut player = AnimationPlayer::new(clip(3, 0.1, true).with_event(0, "start"));
+
+        assert_eq!(player.update(0.01), vec!["start"]);
+        assert!(player.update(0.01).is_empty());
+    }
+
+    #[test]
+    fn clip_without_frames_stops() {
+        let mut player = AnimationPlayer::new(clip(0, 0.1, true));
+
+        assert!(player.update(1.0).is_empty());
+        assert!(!player.playing);
+    }
+
+    #[test]
+    fn zero_length_one_shot_is_over_straight_away() {
+        let mut player = AnimationPlayer::new(clip(3, 0.0, false));
+        player.update(0.016);
+
+        assert!(!player.playing);
+    }
+
+    #[test]
+    fn zero_length_loop_holds_its_frame() {
+        let mut player = AnimationPlayer::new(clip(3, 0.0, true));
+        player.update(10.0);
+
+        assert!(player.playing);
+        assert_eq!(player.current_frame, 0);
+    }
+
+    #[test]
+    fn one_shot_stops_on_its_last_frame() {
+        let mut player = AnimationPlayer::new(clip(3, 0.1, false).with_event(2, "end"));
+
+        assert_eq!(player.update(0.25), vec!["end"]);
+        assert!(player.playing);
+
+        // the last frame is shown for its full duration, then the clip stops there
+        assert!(player.update(10.0).is_empty());
+        assert!(!player.playing);
+        assert_eq!(player.current_frame, 2);
+        assert!(player.update(10.0).is_empty());
+    }
+
+    #[test]
+    fn single_frame_one_shot_finishes() {
+        let mut player = AnimationPlayer::new(clip(1, 0.1, false));
+        player.update(0.05);
+        assert!(player.playing);
+
+        player.update(0.05);
+        assert!(!player.playing);
+    }
+
+    #[test]
+    fn single_frame_loop_keeps_playing() {
+        let mut player = AnimationPlayer::new(clip(1, 0.1, true));
+        player.update(5.0);
+
+        assert!(player.playing);
+        assert_eq!(player.current_frame, 0);
+    }
+
+    #[test]
+    fn reverse_plays_from_the_end() {
+        let mut player = AnimationPlayer::new(clip(3, 0.1, false).with_mode(PlaybackMode::Reverse));
+        assert_eq!(player.current_frame, 2);
+
+        assert_eq!(frames_seen(&mut player, 3, 0.1), vec![1, 0, 0]);
+        assert!(!player.playing);
+    }
+
+    #[test]
+    fn ping_pong_bounces_without_repeating_the_ends() {
+        let mut player = AnimationPlayer::new(clip(3, 0.1, true).with_mode(PlaybackMode::PingPong));
+
+        assert_eq!(frames_seen(&mut player, 6, 0.1), vec![1, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn single_frame_ping_pong_one_shot_finishes() {
+        let mut player = AnimationPlayer::new(clip(1, 0.1, false).with_mode(PlaybackMode::PingPong));
+        player.update(0.2);
+
+        assert!(!player.playing);
+    }
+
+    #[test]
+    fn per_frame_durations_are_respected() {
+        let descriptor = clip(3, 0.1, true).with_frame_durations(vec![0.6, 0.15]);
+        assert_eq!(descriptor.frame_durations, vec![0.6, 0.15, 0.15]);
+
+        let mut player = AnimationPlayer::new(descriptor);
+        assert_eq!(frames_seen(&mut player, 4, 0.15), vec![0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn playback_rate_scales_time() {
+        let mut player = AnimationPlayer::new(clip(4, 0.1, true));
+        player.set_playback_rate(2.0);
+        player.update(0.1);
+        assert_eq!(player.current_frame, 2);
+
+        player.set_playback_rate(0.0);
+        player.update(1.0);
+        assert_eq!(player.current_frame, 2);
+    }
+
+    #[test]
+    fn reset_goes_back_to_the_first_frame_and_replays_its_events() {
+        let mut player = AnimationPlayer::new(clip(3, 0.1, false).with_event(0, "start"));
+        player.update(1.0);
+        player.reset();
+
+        assert!(player.playing);
+        assert_eq!(player.current_frame, 0);
+        assert_eq!(player.update(0.0), vec!["start"]);
+    }
+
+    #[test]
+    fn mirroring_flips_the_source_rect_and_pivot() {
+        let mut player = AnimationPlayer::new(clip(2, 0.1, true).with_pivot(Vector2::new(4.0, 16.0)));
+        let origin = player.origin();
+        assert_eq!((origin.x, origin.y), (4.0, 16.0));
+
+        player.mirrored = true;
+        let origin = player.origin();
+        assert_eq!(player.source_rect().width, -16.0);
+        assert_eq!((origin.x, origin.y), (12.0, 16.0));
+    }
+
+    #[test]
+    fn same_clip_compares_texture_and_frames() {
+        let a = clip(3, 0.1, true);
+        assert!(a.same_clip(&a.clone().with_event(1, "x")));
+        assert!(!a.same_clip(&clip(4, 0.1, true)));
+
+        let mut other_sheet = a.clone();
+        other_sheet.texture = FakeSheet(2);
+        assert!(!a.same_clip(&other_sheet));
+    }
+
+    fn xy(rects: &[Rectangle]) -> Vec<(f32, f32)> {
+        rects.iter().map(|rect| (rect.x, rect.y)).collect()
+    }
+
+    #[test]
+    fn grids_count_whole_frames_inside_the_margin() {
+        // 2px border, 4px gaps: 16px frames start at 2, 22, 42, 62, 82 and the last ends 2px from the edge
+        let grid = SheetGrid::new(16, 16).with_margin(2).with_spacing(4);
+        assert_eq!(grid.column_count(100), 5);
+        assert_eq!(grid.column_count(99), 4);
+        assert_eq!(grid.row_count(40), 2);
+        assert_eq!(grid.row_count(39), 1);
+        assert_eq!(grid.row_count(3), 0);
+        assert_eq!(SheetGrid::new(0, 16).column_count(100), 0);
+
+        let frames = grid.frames(100, 39).unwrap();
+        assert_eq!(xy(&frames), [(2.0, 2.0), (22.0, 2.0), (42.0, 2.0), (62.0, 2.0), (82.0, 2.0)]);
+        assert!(frames.iter().all(|rect| rect.width == 16.0 && rect.height == 16.0));
+    }
+
+    #[test]
+    fn grids_cut_rows_then_columns_from_a_range() {
+        // 4 columns by 3 rows of 32x24
+        let grid = SheetGrid::new(32, 24).with_rows(1..3).with_columns(2..4);
+        let frames = grid.frames(128, 72).unwrap();
+        assert_eq!(xy(&frames), [(64.0, 24.0), (96.0, 24.0), (64.0, 48.0), (96.0, 48.0)]);
+
+        let second_row = SheetGrid::new(32, 24).with_rows(1..2).frames(128, 72).unwrap();
+        assert_eq!(xy(&second_row), [(0.0, 24.0), (32.0, 24.0), (64.0, 24.0), (96.0, 24.0)]);
+        assert_eq!(SheetGrid::new(32, 24).frames(128, 72).unwrap().len(), 12);
+    }
+
+    #[test]
+    fn grids_refuse_cells_off_the_sheet_or_no_cells() {
+        let off_the_side = SheetGrid::new(32, 24).with_columns(3..5).frames(128, 72);
+        assert!(off_the_side.unwrap_err().to_string().contains("only has 3 rows and 4 columns"));
+        assert!(SheetGrid::new(32, 24).with_rows(2..4).frames(128, 72).is_err());
+
+        let nothing = SheetGrid::new(32, 24).with_columns(2..2).frames(128, 72);
+        assert!(nothing.unwrap_err().to_string().contains("selects no frames"));
+        // a sheet too small for one frame
+        assert!(SheetGrid::new(32, 24).frames(16, 16).is_err());
+    }
+
+    // a one frame clip told apart by where its frame is
+    fn at(x: f32) -> AnimationDescriptor {
+        AnimationDescriptor::new(crate::draw::test_sheet(64, 16), vec![Rectangle::new(x, 0.0, 16.0, 16.0)], 0.1, true)
+    }
+
+    fn key(emotion: Emotion, facing: Facing) -> AnimationKey {
+        AnimationKey { pose: Pose::Standing, emotion, facing }
+    }
+
+    fn resolved(bank: &AnimationBank, emotion: Emotion, facing: Facing) -> Option<(f32, bool)> {
+        bank.resolve(&key(emotion, facing)).map(|found| (found.descriptor.frames[0].x, found.mirrored))
+    }
+
+    #[test]
+    fn a_missing_emotion_falls_back_to_neutral() {
+        let mut bank = AnimationBank::new();
+        bank.insert(key(Emotion::Neutral, Facing::Right), at(0.0));
+        bank.insert(key(Emotion::Happy, Facing::Right), at(16.0));
+
+        assert_eq!(resolved(&bank, Emotion::Happy, Facing::Right), Some((16.0, false)));
+        assert_eq!(resolved(&bank, Emotion::Sad, Facing::Right), Some((0.0, false)));
+
+        // nothing for the pose at all
+        let sitting = AnimationKey { pose: Pose::Sitting, ..key(Emotion::Neutral, Facing::Right) };
+        assert!(bank.resolve(&sitting).is_none());
+    }
+
+    #[test]
+    fn left_mirrors_right_unless_it_has_its_own() {
+        let mut bank = AnimationBank::new();
+        bank.insert(key(Emotion::Neutral, Facing::Right), at(0.0));
+        bank.insert(key(Emotion::Happy, Facing::Right), at(16.0));
+
+        // the emotion still wins over Neutral on the way
+        assert_eq!(resolved(&bank, Emotion::Happy, Facing::Left), Some((16.0, true)));
+        assert_eq!(resolved(&bank, Emotion::Sad, Facing::Left), Some((0.0, true)));
+
+        // real left art, even just a Neutral one, beats a mirrored right
+        bank.insert(key(Emotion::Neutral, Facing::Left), at(32.0));
+        assert_eq!(resolved(&bank, Emotion::Happy, Facing::Left), Some((32.0, false)));
+    }
+
+    #[test]
+    fn clips_that_cant_be_mirrored_face_right_anyway() {
+        let mut bank = AnimationBank::new();
+        bank.insert(key(Emotion::Neutral, Facing::Right), at(0.0).with_mirrorable(false));
+        assert_eq!(resolved(&bank, Emotion::Neutral, Facing::Left), Some((0.0, false)));
+    }
+
+    #[test]
+    fn front_and_back_use_the_side_view_as_is() {
+        let mut bank = AnimationBank::new();
+        bank.insert(key(Emotion::Neutral, Facing::Right), at(0.0));
+        assert_eq!(resolved(&bank, Emotion::Neutral, Facing::Front), Some((0.0, false)));
+        assert_eq!(resolved(&bank, Emotion::Happy, Facing::Back), Some((0.0, false)));
+
+        bank.insert(key(Emotion::Neutral, Facing::Front), at(48.0));
+        assert_eq!(resolved(&bank, Emotion::Happy, Facing::Front), Some((48.0, false)));
+        assert_eq!(resolved(&bank, Emotion::Neutral, Facing::Back), Some((0.0, false)));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::animation::{AnimationDescriptor, AnimationPlayer, PlaybackMode, Sheet, TextureHandle};
+
+/// How important a one-shot is. A higher priority one-shot interrupts a
+/// lower one that's already playing; otherwise it waits in the queue.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum OneShotPriority {
+    Low,    // idle flavour: sniffing, scratching
+    Normal, // reactions: bark
+    High,   // things that must be seen: hurt, attack
+}
+
+/// What happened during `Animator::update`, for game logic to react to
+#[derive(Clone, PartialEq, Debug)]
+pub enum AnimationSignal {
+    Event(String),       // a frame event fired (see AnimationDescriptor::events)
+    Finished(String),    // a one-shot played to the end
+    Interrupted(String), // a one-shot was cut off by a higher priority one
+    TransitionFinished,  // the base clip given to play_transition reached its end
+}
+
+struct OneShot<T> {
+    name: String,
+    descriptor: AnimationDescriptor<T>,
+    priority: OneShotPriority,
+}
+
+struct Overlay<T> {
+    name: String,
+    priority: OneShotPriority,
+    player: AnimationPlayer<T>,
+}
+
+/// Layered animation: a looping base clip (idle, walk) plus one-shot clips
+/// (bark, attack, hurt) played over it one at a time. When the last queued
+/// one-shot finishes we drop back to the base clip.
+pub struct Animator<T = Sheet> {
+    base: AnimationPlayer<T>,
+    overlay: Option<Overlay<T>>,
+    // highest priority first, first come first served within a priority
+    queue: Vec<OneShot<T>>,
+    // one-shot art faces right; flip it when the owner faces left
+    mirror_one_shots: bool,
+    // interruptions etc. that happened between updates, reported by the next one
+    pending: Vec<AnimationSignal>,
+    // the base is a transition clip playing once rather than a loop
+    in_transition: bool,
+}
+
+impl<T: TextureHandle> Animator<T> {
+    pub fn new(base: AnimationDescriptor<T>, mirrored: bool) -> Self {
+        let mut player = AnimationPlayer::new(base);
+        player.mirrored = mirrored;
+
+        Self { base: player, overlay: None, queue: Vec::new(), mirror_one_shots: false, pending: Vec::new(), in_transition: false }
+    }
+
+    /// Swap the looping clip underneath. One-shots keep playing on top.
+    /// Setting the clip that's already playing (e.g. just turning around)
+    /// keeps its frame and timing rather than starting over.
+    pub fn set_base(&mut self, descriptor: AnimationDescriptor<T>, mirrored: bool) {
+        self.base.mirrored = mirrored;
+        if !self.in_transition && self.base.descriptor.same_clip(&descriptor) {
+            return;
+        }
+
+        self.in_transition = false;
+        self.base.descriptor = descriptor;
+        self.base.reset();
+    }
+
+    /// Play `descriptor` once as the base, e.g. lying down between standing and
+    /// sleeping. `update` reports TransitionFinished when it ends, and the last
+    /// frame is held until the next `set_base`.
+    pub fn play_transition(&mut self, descriptor: AnimationDescriptor<T>, reversed: bool, mirrored: bool) {
+        let mut descriptor = descriptor;
+        descriptor.looped = false;
+        if reversed {
+            descriptor.mode = match descriptor.mode {
+                PlaybackMode::Forward => PlaybackMode::Reverse,
+                PlaybackMode::Reverse => PlaybackMode::Forward,
+                PlaybackMode::PingPong => PlaybackMode::PingPong,
+            };
+        }
+
+        self.in_transition = true;
+        self.base.descriptor = descriptor;
+        self.base.mirrored = mirrored;
+        self.base.reset();
+    }
+
+    /// Flip the one-shots, e.g. when the dog turns around mid-bark. The base
+    /// clip's flip comes from `set_base` instead, since it may have real left-facing art.
+    pub fn set_one_shots_mirrored(&mut self, mirrored: bool) {
+        self.mirror_one_shots = mirrored;
+        if let Some(overlay) = &mut self.overlay {
+            overlay.player.mirrored = mirrored && overlay.player.descriptor.mirrorable;
+        }
+    }
+
+    /// Play `descriptor` once over the base clip, now or when its turn comes
+    pub fn play_once(&mut self, name: &str, descriptor: AnimationDescriptor<T>, priority: OneShotPriority) {
+        let shot = OneShot { name: name.to_string(), descriptor, priority };
+
+        match &self.overlay {
+            None => self.start(shot),
+            Some(current) if priority > current.priority => {
+                self.pending.push(AnimationSignal::Interrupted(current.name.clone()));
+                self.start(shot);
+            },
+            Some(_) => {
+                let at = self.queue.iter().position(|q| q.priority < priority).unwrap_or(self.queue.len());
+                self.queue.insert(at, shot);
+            },
+        }
+    }
+
+    fn start(&mut self, shot: OneShot<T>) {
+        let mut player = AnimationPlayer::new(shot.descriptor);
+        // a one-shot is never looped, whatever the descriptor says
+        player.descriptor.looped = false;
+        player.mirrored = self.mirror_one_shots && player.descriptor.mirrorable;
+
+        self.overlay = Some(Overlay { name: shot.name, priority: shot.priority, player });
+    }
+
+    /// Advance by dt seconds. The base clip holds still while a one-shot plays.
+    pub fn update(&mut self, dt: f32) -> Vec<AnimationSignal> {
+        let mut signals: Vec<AnimationSignal> = self.pending.drain(..).collect();
+
+        let Some(overlay) = &mut self.overlay else {
+            signals.extend(self.base.update(dt).into_iter().map(AnimationSignal::Event));
+            if self.in_transition && !self.base.playing {
+                self.in_transition = false;
+                signals.push(AnimationSignal::TransitionFinished);
+            }
+            return signals;
+        };
+
+        signals.extend(overlay.player.update(dt).into_iter().map(AnimationSignal::Event));
+
+        if !overlay.player.playing {
+            signals.push(AnimationSignal::Finished(overlay.name.clone()));
+            self.overlay = None;
+
+            if !self.queue.is_empty() {
+                let next = self.queue.remove(0);
+                self.start(next);
+            } else if !self.in_transition {
+                self.base.reset();
+            }
+        }
+
+        signals
+    }
+
+    /// The player whose frame should be drawn right now
+    pub fn current(&self) -> &AnimationPlayer<T> {
+        match &self.overlay {
+            Some(overlay) => &overlay.player,
+            None => &self.base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::tests::{clip, FakeSheet};
+
+    // 4 frame idle under everything. the one-shots are told apart by their frame count
+    fn animator() -> Animator<FakeSheet> {
+        Animator::new(clip(4, 0.1, true), false)
+    }
+
+    fn run(animator: &mut Animator<FakeSheet>, secs: f32) -> Vec<AnimationSignal> {
+        (0..(secs / 0.05).round() as usize).flat_map(|_| animator.update(0.05)).collect()
+    }
+
+    fn finished(signals: &[AnimationSignal]) -> Vec<&str> {
+        signals.iter().filter_map(|signal| match signal {
+            AnimationSignal::Finished(name) => Some(name.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn one_shots_of_the_same_priority_play_in_turn() {
+        let mut animator = animator();
+        animator.play_once("bark", clip(2, 0.1, false), OneShotPriority::Normal);
+        animator.play_once("growl", clip(3, 0.1, false), OneShotPriority::Normal);
+        animator.play_once("whine", clip(5, 0.1, false), OneShotPriority::Normal);
+
+        assert_eq!(animator.current().descriptor.frames.len(), 2);
+        let signals = run(&mut animator, 2.0);
+        assert_eq!(finished(&signals), ["bark", "growl", "whine"]);
+        assert!(!signals.iter().any(|signal| matches!(signal, AnimationSignal::Interrupted(_))));
+    }
+
+    #[test]
+    fn a_higher_priority_one_shot_cuts_in() {
+        let mut animator = animator();
+        animator.play_once("sniff", clip(3, 0.1, false), OneShotPriority::Low);
+        animator.update(0.05);
+
+        animator.play_once("hurt", clip(5, 0.1, false), OneShotPriority::High);
+        assert_eq!(animator.current().descriptor.frames.len(), 5);
+
+        // lower ones wait, the most important first
+        animator.play_once("sniff", clip(3, 0.1, false), OneShotPriority::Low);
+        animator.play_once("bark", clip(2, 0.1, false), OneShotPriority::Normal);
+
+        let signals = run(&mut animator, 2.0);
+        assert_eq!(signals[0], AnimationSignal::Interrupted("sniff".to_string()));
+        assert_eq!(finished(&signals), ["hurt", "bark", "sniff"]);
+    }
+
+    #[test]
+    fn a_one_shot_never_loops_and_hands_back_to_the_base_clip() {
+        let mut animator = animator();
+        animator.update(0.25);
+        assert_eq!(animator.current().current_frame, 2);
+
+        // even when the clip says it loops
+        animator.play_once("bark", clip(2, 0.1, true), OneShotPriority::Normal);
+        assert!(!animator.current().descriptor.looped);
+        let mut signals = Vec::new();
+        while finished(&signals).is_empty() {
+            assert_eq!(animator.current().descriptor.frames.len(), 2, "the bark ended without saying so");
+            signals = animator.update(0.05);
+        }
+
+        // the idle starts over rather than carrying on from where it was
+        assert_eq!(animator.current().descriptor.frames.len(), 4);
+        assert_eq!(animator.current().current_frame, 0);
+        assert!(animator.current().playing);
+    }
+}
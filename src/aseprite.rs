@@ -0,0 +1,202 @@
+use crate::animation::PlaybackMode;
+use crate::manifest::{relative_to, ClipManifest};
+use serde::Deserialize;
+use std::path::Path;
+use anyhow::{bail, Context};
+
+// the parts of Aseprite's "Export Sprite Sheet" JSON we care about.
+// both the hash and the array flavour of `frames` are accepted
+
+#[derive(Deserialize, Debug)]
+pub struct AsepriteSheet {
+    pub frames: AsepriteFrames,
+    pub meta: AsepriteMeta,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum AsepriteFrames {
+    Array(Vec<AsepriteFrame>),
+    // keyed by frame name, in export order (serde_json preserve_order)
+    Hash(serde_json::Map<String, serde_json::Value>),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AsepriteFrame {
+    pub frame: AsepriteRect,
+    pub duration: u32, // ms
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct AsepriteRect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AsepriteMeta {
+    pub image: String,
+    #[serde(default, rename = "frameTags")]
+    pub frame_tags: Vec<AsepriteTag>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AsepriteTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    #[serde(default = "default_direction")]
+    pub direction: String,
+    // newer Aseprite versions write how often to play, "1" = once. missing = forever
+    #[serde(default)]
+    pub repeat: Option<String>,
+}
+
+fn default_direction() -> String { "forward".to_string() }
+
+impl AsepriteFrames {
+    pub fn to_vec(&self) -> anyhow::Result<Vec<AsepriteFrame>> {
+        match self {
+            AsepriteFrames::Array(frames) => Ok(frames.clone()),
+            AsepriteFrames::Hash(map) => map.iter()
+                .map(|(name, value)| {
+                    serde_json::from_value(value.clone()).with_context(|| format!("frame '{}'", name))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl AsepriteSheet {
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// One clip per frame tag, or a single clip named after the image when
+    /// the file has no tags. `sheet` is the image path to put in the clips.
+    pub fn clips(&self, sheet: &str) -> anyhow::Result<Vec<ClipManifest>> {
+        let frames = self.frames.to_vec()?;
+        if frames.is_empty() {
+            bail!("export has no frames");
+        }
+
+        let untagged;
+        let tags = if self.meta.frame_tags.is_empty() {
+            let name = Path::new(&self.meta.image).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+            untagged = [AsepriteTag { name, from: 0, to: frames.len() - 1, direction: default_direction(), repeat: None }];
+            &untagged[..]
+        } else {
+            &self.meta.frame_tags[..]
+        };
+
+        let mut clips = Vec::new();
+        for tag in tags {
+            if tag.from > tag.to || tag.to >= frames.len() {
+                bail!("tag '{}' covers frames {}..={} but the export has {}", tag.name, tag.from, tag.to, frames.len());
+            }
+            let mode = match tag.direction.as_str() {
+                "forward" => PlaybackMode::Forward,
+                "reverse" => PlaybackMode::Reverse,
+                // pingpong_reverse starts at the end, close enough for now
+                "pingpong" | "pingpong_reverse" => PlaybackMode::PingPong,
+                other => bail!("tag '{}' has unknown direction '{}'", tag.name, other),
+            };
+            let looped = match tag.repeat.as_deref() {
+                None | Some("0") => true,
+                Some(_) => false,
+            };
+
+            let tagged = &frames[tag.from..=tag.to];
+            clips.push(ClipManifest {
+                name: tag.name.clone(),
+                sheet: sheet.to_string(),
+                grid: None,
+                rects: tagged.iter().map(|f| [f.frame.x, f.frame.y, f.frame.w, f.frame.h]).collect(),
+                frame_ms: tagged[0].duration,
+                durations_ms: tagged.iter().map(|f| f.duration).collect(),
+                looped,
+                mode,
+                mirrorable: true,
+                pivot: None,
+                events: Vec::new(),
+            });
+        }
+
+        Ok(clips)
+    }
+}
+
+/// Reads an Aseprite JSON export into manifest clips. The image path in the
+/// export is relative to the JSON file, like the manifest's own paths.
+pub fn import(path: &str) -> anyhow::Result<Vec<ClipManifest>> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("reading {}", path))?;
+    let sheet = AsepriteSheet::parse(&json).with_context(|| format!("parsing {}", path))?;
+
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    let image = relative_to(dir, &sheet.meta.image);
+    sheet.clips(&image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(x: u32, duration: u32) -> String {
+        format!(r#"{{ "frame": {{ "x": {}, "y": 0, "w": 16, "h": 16 }}, "duration": {} }}"#, x, duration)
+    }
+
+    #[test]
+    fn frame_tags_become_clips() {
+        let json = format!(r#"{{
+            "frames": [ {}, {}, {}, {} ],
+            "meta": {{
+                "image": "cat.png",
+                "frameTags": [
+                    {{ "name": "walk", "from": 0, "to": 2, "direction": "pingpong" }},
+                    {{ "name": "pounce", "from": 3, "to": 3, "direction": "forward", "repeat": "1" }}
+                ]
+            }}
+        }}"#, frame(0, 100), frame(16, 120), frame(32, 140), frame(48, 500));
+
+        let clips = AsepriteSheet::parse(&json).unwrap().clips("images/cat.png").unwrap();
+        assert_eq!(clips.len(), 2);
+
+        let walk = &clips[0];
+        assert_eq!((walk.name.as_str(), walk.sheet.as_str()), ("walk", "images/cat.png"));
+        assert_eq!(walk.rects, vec![[0.0, 0.0, 16.0, 16.0], [16.0, 0.0, 16.0, 16.0], [32.0, 0.0, 16.0, 16.0]]);
+        assert_eq!(walk.durations_ms, vec![100, 120, 140]);
+        assert_eq!(walk.mode, PlaybackMode::PingPong);
+        assert!(walk.looped);
+        assert!(walk.problems().is_empty(), "{:?}", walk.problems());
+
+        let pounce = &clips[1];
+        assert_eq!(pounce.rects, vec![[48.0, 0.0, 16.0, 16.0]]);
+        assert!(!pounce.looped);
+    }
+
+    #[test]
+    fn hash_frames_without_tags_make_one_clip_named_after_the_image() {
+        let json = format!(r#"{{
+            "frames": {{ "cat 0.aseprite": {}, "cat 1.aseprite": {} }},
+            "meta": {{ "image": "sprites/cat_idle.png" }}
+        }}"#, frame(0, 100), frame(16, 100));
+
+        let clips = AsepriteSheet::parse(&json).unwrap().clips("cat_idle.png").unwrap();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].name, "cat_idle");
+        assert_eq!(clips[0].rects.len(), 2);
+    }
+
+    #[test]
+    fn bad_tags_are_errors() {
+        let export = |tag: &str| format!(r#"{{ "frames": [ {} ], "meta": {{ "image": "cat.png", "frameTags": [ {} ] }} }}"#, frame(0, 100), tag);
+
+        let past_the_end = export(r#"{ "name": "walk", "from": 0, "to": 1 }"#);
+        assert!(AsepriteSheet::parse(&past_the_end).unwrap().clips("cat.png").is_err());
+
+        let sideways = export(r#"{ "name": "walk", "from": 0, "to": 0, "direction": "sideways" }"#);
+        assert!(AsepriteSheet::parse(&sideways).unwrap().clips("cat.png").is_err());
+    }
+}
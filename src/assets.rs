@@ -0,0 +1,221 @@
+use crate::animation::{AnimationBank, AnimationDescriptor, AnimationKey, Emotion, Facing, Pose};
+use crate::manifest::SpriteManifest;
+use raylib::prelude::{Image, Texture2D, Rectangle};
+use raylib::color::Color;
+use raylib::{RaylibHandle, RaylibThread};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+const PLACEHOLDER_SIZE: i32 = 64;
+
+/// Why an asset couldn't be loaded
+#[derive(Debug)]
+pub enum AssetError {
+    Missing { path: String },
+    Malformed { path: String, reason: String },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Missing { path } => write!(f, "missing asset {}", path),
+            AssetError::Malformed { path, reason } => write!(f, "malformed asset {}: {}", path, reason),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+/// Every texture and sprite set the game has loaded, keyed by path, so
+/// twenty dogs share one copy of each sheet. The Arc count is the ref count:
+/// `release_unused` drops whatever nobody holds anymore.
+pub struct Assets {
+    textures: HashMap<String, Arc<Texture2D>>,
+    banks: HashMap<String, Arc<AnimationBank>>,
+    // magenta checkerboard drawn in place of anything that failed to load
+    placeholder: Arc<Texture2D>,
+}
+
+impl Assets {
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> anyhow::Result<Self> {
+        let image = Image::gen_image_checked(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, 8, 8, Color::MAGENTA, Color::BLACK);
+        let placeholder = rl.load_texture_from_image(thread, &image)
+            .map_err(|err| anyhow::anyhow!("creating the placeholder texture: {}", err))?;
+
+        Ok(Self::with_placeholder(Arc::new(placeholder)))
+    }
+
+    /// An empty cache that falls back to `placeholder`, for when there's no
+    /// window to make the checkerboard with
+    pub fn with_placeholder(placeholder: Arc<Texture2D>) -> Self {
+        Self { textures: HashMap::new(), banks: HashMap::new(), placeholder }
+    }
+
+    /// The texture at `path`, loaded on first use
+    pub fn texture(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &str) -> Result<Arc<Texture2D>, AssetError> {
+        self.texture_with(path, |path| rl.load_texture(thread, path).map_err(|err| err.to_string()))
+    }
+
+    // the cache around `load`, which only runs for a file that exists and isn't cached yet
+    fn texture_with(&mut self, path: &str, load: impl FnOnce(&str) -> Result<Texture2D, String>) -> Result<Arc<Texture2D>, AssetError> {
+        if let Some(texture) = self.textures.get(path) {
+            return Ok(texture.clone());
+        }
+
+        if !Path::new(path).is_file() {
+            return Err(AssetError::Missing { path: path.to_string() });
+        }
+        let texture = load(path).map_err(|reason| AssetError::Malformed { path: path.to_string(), reason })?;
+
+        let texture = Arc::new(texture);
+        self.textures.insert(path.to_string(), texture.clone());
+        Ok(texture)
+    }
+
+    /// Like `texture`, but logs the problem and hands back the placeholder
+    /// so the game keeps running with an obviously wrong sprite
+    pub fn texture_or_placeholder(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &str) -> Arc<Texture2D> {
+        match self.texture(rl, thread, path) {
+            Ok(texture) => texture,
+            Err(err) => {
+                eprintln!("{}, using placeholder", err);
+                self.placeholder.clone()
+            }
+        }
+    }
+
+    /// The sprite set described by the manifest at `path`, loaded on first use
+    pub fn animations(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &str) -> Result<Arc<AnimationBank>, AssetError> {
+        if let Some(bank) = self.banks.get(path) {
+            return Ok(bank.clone());
+        }
+
+        if !Path::new(path).is_file() {
+            return Err(AssetError::Missing { path: path.to_string() });
+        }
+        let manifest = SpriteManifest::load(path)
+            .map_err(|err| AssetError::Malformed { path: path.to_string(), reason: format!("{:#}", err) })?;
+        let bank = manifest.into_bank(self, rl, thread)
+            .map_err(|err| AssetError::Malformed { path: path.to_string(), reason: format!("{:#}", err) })?;
+
+        let bank = Arc::new(bank);
+        self.banks.insert(path.to_string(), bank.clone());
+        Ok(bank)
+    }
+
+    /// Like `animations`, but falls back to the placeholder for every pose
+    pub fn animations_or_placeholder(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &str) -> Arc<AnimationBank> {
+        match self.animations(rl, thread, path) {
+            Ok(bank) => bank,
+            Err(err) => {
+                eprintln!("{}, using placeholder", err);
+                Arc::new(self.placeholder_bank())
+            }
+        }
+    }
+
+    fn placeholder_bank(&self) -> AnimationBank {
+        let size = PLACEHOLDER_SIZE as f32;
+        let frames = vec![Rectangle::new(0.0, 0.0, size, size)];
+        let descriptor = AnimationDescriptor::new(self.placeholder.clone(), frames, 1.0, true);
+
+        let mut bank = AnimationBank::new();
+        bank.insert_clip("placeholder", descriptor);
+        for pose in [Pose::Sitting, Pose::Walking, Pose::Sleeping, Pose::Standing, Pose::HindLegs] {
+            bank.bind(AnimationKey { pose, emotion: Emotion::Neutral, facing: Facing::Right }, "placeholder");
+        }
+        bank
+    }
+
+    /// Unloads sprite sets, then textures, that only the cache still holds.
+    /// Returns how many textures were freed.
+    pub fn release_unused(&mut self) -> usize {
+        self.banks.retain(|_, bank| Arc::strong_count(bank) > 1);
+
+        let before = self.textures.len();
+        self.textures.retain(|_, texture| Arc::strong_count(texture) > 1);
+        before - self.textures.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draw;
+    use std::cell::Cell;
+
+    const GRASS: &str = "images/forest_trees/8/1.png";
+    const TREES: &str = "images/forest_trees/8/2.png";
+
+    fn assets() -> Assets {
+        Assets::with_placeholder(draw::test_sheet(64, 64))
+    }
+
+    fn fake_texture(_: &str) -> Result<Texture2D, String> {
+        Ok(Arc::try_unwrap(draw::test_sheet(16, 16)).ok().unwrap())
+    }
+
+    #[test]
+    fn a_texture_is_loaded_once_and_shared() {
+        let mut assets = assets();
+        let loads = Cell::new(0);
+        let counted = |path: &str| {
+            loads.set(loads.get() + 1);
+            fake_texture(path)
+        };
+
+        let first = assets.texture_with(GRASS, counted).unwrap();
+        let again = assets.texture_with(GRASS, counted).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(loads.get(), 1);
+
+        assets.texture_with(TREES, counted).unwrap();
+        assert_eq!(loads.get(), 2);
+    }
+
+    #[test]
+    fn load_failures_say_which_file_and_why() {
+        let mut assets = assets();
+
+        let missing = assets.texture_with("images/nowhere.png", fake_texture);
+        assert!(matches!(&missing, Err(AssetError::Missing { path }) if path == "images/nowhere.png"));
+
+        let broken = assets.texture_with(GRASS, |_| Err("not a png".to_string()));
+        let Err(AssetError::Malformed { path, reason }) = &broken else { panic!("{:?}", broken.map(|_| ())) };
+        assert_eq!((path.as_str(), reason.as_str()), (GRASS, "not a png"));
+        assert_eq!(broken.unwrap_err().to_string(), format!("malformed asset {}: not a png", GRASS));
+
+        // nothing was cached, so the next try loads again
+        assert!(assets.texture_with(GRASS, fake_texture).is_ok());
+    }
+
+    #[test]
+    fn release_unused_frees_only_what_nobody_holds() {
+        let mut assets = assets();
+        let held = assets.texture_with(GRASS, fake_texture).unwrap();
+        assets.texture_with(TREES, fake_texture).unwrap();
+
+        assert_eq!(assets.release_unused(), 1);
+        assert_eq!(assets.release_unused(), 0);
+
+        // still cached while it's held, and freed once it isn't
+        let loaded_again = Cell::new(false);
+        let again = assets.texture_with(GRASS, |path| { loaded_again.set(true); fake_texture(path) }).unwrap();
+        assert!(Arc::ptr_eq(&held, &again) && !loaded_again.get());
+
+        drop((held, again));
+        assert_eq!(assets.release_unused(), 1);
+    }
+
+    #[test]
+    fn the_placeholder_stands_in_for_every_pose() {
+        let assets = assets();
+        let bank = assets.placeholder_bank();
+        for pose in [Pose::Sitting, Pose::Walking, Pose::Sleeping, Pose::Standing, Pose::HindLegs] {
+            let found = bank.resolve(&AnimationKey { pose, emotion: Emotion::Happy, facing: Facing::Left }).unwrap();
+            assert!(Arc::ptr_eq(&found.descriptor.texture, &assets.placeholder));
+        }
+    }
+}
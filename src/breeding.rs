@@ -0,0 +1,288 @@
+use crate::dog::{Dog, DogBreed, DogId};
+use crate::genetics::{Genome, mix_breeds};
+use crate::rng::Rng;
+use crate::types::Gender;
+use anyhow::bail;
+use std::collections::HashMap;
+
+// dogs younger than this can't have litters
+pub const MIN_BREEDING_AGE_DAYS: i64 = 365;
+// a dam needs this long to recover between litters
+pub const LITTER_GAP_DAYS: i64 = 180;
+
+/// Where a dog came from. Founders (shelter dogs, the starting dog) have
+/// no recorded parents.
+#[derive(Clone, Copy, Debug)]
+pub struct Lineage {
+    pub sire: Option<DogId>,
+    pub dam: Option<DogId>,
+    pub generation: u32,
+}
+
+impl Lineage {
+    pub fn founder() -> Self {
+        Self { sire: None, dam: None, generation: 0 }
+    }
+}
+
+/// A newborn before it has a name and sprites
+pub struct Puppy {
+    pub breed: DogBreed,
+    pub gender: Gender,
+    pub genome: Genome,
+    pub lineage: Lineage,
+}
+
+/// Checks that `sire` and `dam` can have a litter together on `today`
+pub fn check_compatible(sire: &Dog, dam: &Dog, today: chrono::NaiveDate) -> anyhow::Result<()> {
+    if sire.gender != Gender::Boy {
+        bail!("{} can't sire a litter, she's a girl", sire.name);
+    }
+    if dam.gender != Gender::Girl {
+        bail!("{} can't carry a litter, he's a boy", dam.name);
+    }
+
+    for dog in [sire, dam] {
+        let age_days = (today - dog.date_of_birth).num_days();
+        if age_days < MIN_BREEDING_AGE_DAYS {
+            bail!("{} is too young to have puppies", dog.name);
+        }
+    }
+
+    if are_close_relatives(sire, dam) {
+        bail!("{} and {} are too closely related", sire.name, dam.name);
+    }
+
+    Ok(())
+}
+
+fn are_close_relatives(a: &Dog, b: &Dog) -> bool {
+    // parent / child
+    if a.lineage.sire == Some(b.id) || a.lineage.dam == Some(b.id) {
+        return true;
+    }
+    if b.lineage.sire == Some(a.id) || b.lineage.dam == Some(a.id) {
+        return true;
+    }
+
+    // siblings, full or half
+    let shares = |x: Option<DogId>, y: Option<DogId>| x.is_some() && x == y;
+    shares(a.lineage.sire, b.lineage.sire) || shares(a.lineage.dam, b.lineage.dam)
+}
+
+/// Rolls a litter for a compatible pair. Litter size follows the dam's size.
+pub fn whelp_litter(sire: &Dog, dam: &Dog, today: chrono::NaiveDate, rng: &mut Rng) -> anyhow::Result<Vec<Puppy>> {
+    check_compatible(sire, dam, today)?;
+
+    let (min, max) = dam.genome.size().litter_range();
+    let count = rng.range(min, max + 1);
+
+    let breed = mix_breeds(sire.breed, dam.breed);
+    let generation = sire.lineage.generation.max(dam.lineage.generation) + 1;
+
+    let mut litter = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let gender = if rng.chance(0.5) { Gender::Boy } else { Gender::Girl };
+        litter.push(Puppy {
+            breed,
+            gender,
+            genome: Genome::cross(&sire.genome, &dam.genome, rng),
+            lineage: Lineage { sire: Some(sire.id), dam: Some(dam.id), generation },
+        });
+    }
+
+    Ok(litter)
+}
+
+/// One dog as remembered by the pedigree, even after it's gone from the game
+#[derive(Clone, Debug)]
+pub struct PedigreeEntry {
+    pub name: String,
+    pub breed: DogBreed,
+    pub gender: Gender,
+    pub date_of_birth: chrono::NaiveDate,
+    pub lineage: Lineage,
+}
+
+/// Family tree node, built on demand for the pedigree view
+pub struct PedigreeNode {
+    pub id: DogId,
+    pub entry: PedigreeEntry,
+    pub sire: Option<Box<PedigreeNode>>,
+    pub dam: Option<Box<PedigreeNode>>,
+}
+
+/// Every dog we've ever owned or bred, keyed by id
+pub struct Pedigree {
+    entries: HashMap<DogId, PedigreeEntry>,
+}
+
+impl Pedigree {
+    pub fn new() -> Self {
+        Self { entries: HashMap::new() }
+    }
+
+    pub fn record(&mut self, dog: &Dog) {
+        self.entries.insert(dog.id, PedigreeEntry {
+            name: dog.name.clone(),
+            breed: dog.breed,
+            gender: dog.gender,
+            date_of_birth: dog.date_of_birth,
+            lineage: dog.lineage,
+        });
+    }
+
+    pub fn get(&self, id: DogId) -> Option<&PedigreeEntry> {
+        self.entries.get(&id)
+    }
+
+    /// When `dam` last had puppies, if she ever has
+    pub fn last_litter(&self, dam: DogId) -> Option<chrono::NaiveDate> {
+        self.entries.values()
+            .filter(|entry| entry.lineage.dam == Some(dam))
+            .map(|entry| entry.date_of_birth)
+            .max()
+    }
+
+    /// Family tree of `id` going back `generations` levels
+    pub fn tree(&self, id: DogId, generations: u32) -> Option<PedigreeNode> {
+        let entry = self.entries.get(&id)?.clone();
+
+        let (sire, dam) = if generations == 0 {
+            (None, None)
+        } else {
+            (
+                entry.lineage.sire.and_then(|p| self.tree(p, generations - 1)).map(Box::new),
+                entry.lineage.dam.and_then(|p| self.tree(p, generations - 1)).map(Box::new),
+            )
+        };
+
+        Some(PedigreeNode { id, entry, sire, dam })
+    }
+
+    /// Indented text version of the tree, one dog per line
+    pub fn describe(&self, id: DogId, generations: u32) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(node) = self.tree(id, generations) {
+            describe_node(&node, "", 0, &mut lines);
+        }
+        lines
+    }
+}
+
+fn describe_node(node: &PedigreeNode, role: &str, depth: usize, lines: &mut Vec<String>) {
+    lines.push(format!(
+        "{}{}{} ({:?}, born {})",
+        "  ".repeat(depth), role, node.entry.name, node.entry.breed, node.entry.date_of_birth
+    ));
+
+    if let Some(sire) = &node.sire {
+        describe_node(sire, "sire: ", depth + 1, lines);
+    }
+    if let Some(dam) = &node.dam {
+        describe_node(dam, "dam: ", depth + 1, lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dog::tests::test_dog;
+    use crate::genetics::DogSize;
+
+    const TODAY: chrono::NaiveDate = chrono::NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+
+    fn adult(gender: Gender, breed: DogBreed) -> Dog {
+        let (mut dog, _) = test_dog();
+        dog.gender = gender;
+        dog.breed = breed;
+        dog.genome = Genome::for_breed(breed);
+        dog.date_of_birth = TODAY - chrono::TimeDelta::days(3 * 365);
+        dog
+    }
+
+    fn child_of(sire: &Dog, dam: &Dog, gender: Gender) -> Dog {
+        let mut child = adult(gender, DogBreed::Labrador);
+        child.lineage = Lineage { sire: Some(sire.id), dam: Some(dam.id), generation: 1 };
+        child
+    }
+
+    #[test]
+    fn unrelated_grown_dogs_are_compatible() {
+        let sire = adult(Gender::Boy, DogBreed::Labrador);
+        let dam = adult(Gender::Girl, DogBreed::Poodle);
+        assert!(check_compatible(&sire, &dam, TODAY).is_ok());
+    }
+
+    #[test]
+    fn close_relatives_are_rejected() {
+        let sire = adult(Gender::Boy, DogBreed::Labrador);
+        let dam = adult(Gender::Girl, DogBreed::Labrador);
+        let son = child_of(&sire, &dam, Gender::Boy);
+        let daughter = child_of(&sire, &dam, Gender::Girl);
+
+        // parent and child, either way round
+        assert!(check_compatible(&sire, &daughter, TODAY).is_err());
+        assert!(check_compatible(&son, &dam, TODAY).is_err());
+        // full siblings
+        assert!(check_compatible(&son, &daughter, TODAY).is_err());
+
+        // half siblings through either parent
+        let other_dam = adult(Gender::Girl, DogBreed::Poodle);
+        let other_sire = adult(Gender::Boy, DogBreed::Poodle);
+        assert!(check_compatible(&son, &child_of(&sire, &other_dam, Gender::Girl), TODAY).is_err());
+        assert!(check_compatible(&son, &child_of(&other_sire, &dam, Gender::Girl), TODAY).is_err());
+
+        // cousins are fine
+        let cousin = child_of(&other_sire, &other_dam, Gender::Girl);
+        assert!(check_compatible(&son, &cousin, TODAY).is_ok());
+    }
+
+    #[test]
+    fn needs_a_grown_boy_and_a_grown_girl() {
+        let boy = adult(Gender::Boy, DogBreed::Labrador);
+        let girl = adult(Gender::Girl, DogBreed::Labrador);
+        assert!(check_compatible(&girl, &boy, TODAY).is_err());
+        assert!(check_compatible(&boy, &adult(Gender::Boy, DogBreed::Poodle), TODAY).is_err());
+
+        let mut young = adult(Gender::Girl, DogBreed::Poodle);
+        young.date_of_birth = TODAY - chrono::TimeDelta::days(MIN_BREEDING_AGE_DAYS - 1);
+        assert!(check_compatible(&boy, &young, TODAY).is_err());
+    }
+
+    #[test]
+    fn litter_size_follows_the_dam() {
+        let sire = adult(Gender::Boy, DogBreed::Poodle);
+        for dam_breed in [DogBreed::Havanese, DogBreed::Corgie, DogBreed::Pitbull, DogBreed::Labrador, DogBreed::GreatDane] {
+            let dam = adult(Gender::Girl, dam_breed);
+            let (min, max) = dam.genome.size().litter_range();
+
+            let sizes: Vec<usize> = (0..200)
+                .map(|seed| whelp_litter(&sire, &dam, TODAY, &mut Rng::new(seed)).unwrap().len())
+                .collect();
+            assert!(sizes.iter().all(|&n| n as i64 >= min && n as i64 <= max), "{:?}: {:?}", dam_breed, sizes);
+            // every size in the range turns up
+            for n in min..=max {
+                assert!(sizes.contains(&(n as usize)), "{:?} never had {} puppies", dam_breed, n);
+            }
+        }
+        assert_eq!(adult(Gender::Girl, DogBreed::GreatDane).genome.size(), DogSize::Giant);
+    }
+
+    #[test]
+    fn puppies_remember_their_parents() {
+        let sire = adult(Gender::Boy, DogBreed::Poodle);
+        let mut dam = adult(Gender::Girl, DogBreed::Labrador);
+        dam.lineage.generation = 2;
+
+        let litter = whelp_litter(&sire, &dam, TODAY, &mut Rng::new(5)).unwrap();
+        let again = whelp_litter(&sire, &dam, TODAY, &mut Rng::new(5)).unwrap();
+        assert_eq!(litter.len(), again.len());
+
+        for (puppy, twin) in litter.iter().zip(&again) {
+            assert_eq!(puppy.breed, DogBreed::Labradoodle);
+            assert_eq!((puppy.lineage.sire, puppy.lineage.dam, puppy.lineage.generation), (Some(sire.id), Some(dam.id), 3));
+            assert_eq!((puppy.gender, &puppy.genome), (twin.gender, &twin.genome));
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use raylib::prelude::{Camera2D, Rectangle, Vector2};
+
+/// Whole-number zooms only, so every art pixel stays the same size on screen
+pub const ZOOM_LEVELS: [f32; 4] = [1.0, 2.0, 3.0, 4.0];
+
+// how quickly the camera catches up, per second. higher is snappier
+const SMOOTHING: f32 = 6.0;
+// the followed thing can wander this far from the centre, in screen pixels,
+// before the camera starts moving
+const DEAD_ZONE_W: f32 = 48.0;
+const DEAD_ZONE_H: f32 = 32.0;
+
+/// Looks at the world (map pixels) and follows whatever it's told to,
+/// easing towards it and never showing past the edge of the map
+pub struct GameCamera {
+    // world point at the centre of the screen
+    target: Vector2,
+    // where `target` is easing towards
+    goal: Vector2,
+    zoom_level: usize,
+    bounds: Option<Rectangle>,
+    screen_w: f32,
+    screen_h: f32,
+}
+
+impl GameCamera {
+    pub fn new(screen_w: f32, screen_h: f32) -> Self {
+        Self {
+            target: Vector2::new(0.0, 0.0),
+            goal: Vector2::new(0.0, 0.0),
+            // the screen is already scaled up to the window, so 1x still looks chunky
+            zoom_level: 0,
+            bounds: None,
+            screen_w,
+            screen_h,
+        }
+    }
+
+    /// After the window changes size, so the view stays centred and in bounds
+    pub fn set_screen_size(&mut self, screen_w: f32, screen_h: f32) {
+        self.screen_w = screen_w;
+        self.screen_h = screen_h;
+        self.target = self.clamped(self.target);
+    }
+
+    pub fn zoom(&self) -> f32 {
+        ZOOM_LEVELS[self.zoom_level]
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.zoom_level = (self.zoom_level + 1).min(ZOOM_LEVELS.len() - 1);
+        self.target = self.clamped(self.target);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom_level = self.zoom_level.saturating_sub(1);
+        self.target = self.clamped(self.target);
+    }
+
+    /// The part of the world the camera may show, None to roam freely
+    pub fn set_bounds(&mut self, bounds: Option<Rectangle>) {
+        self.bounds = bounds;
+        self.target = self.clamped(self.target);
+    }
+
+    /// Jump straight to a point, e.g. after switching maps
+    pub fn snap_to(&mut self, point: Vector2) {
+        self.goal = point;
+        self.target = self.clamped(point);
+    }
+
+    /// Call every tick with the position of whatever is being followed
+    pub fn follow(&mut self, point: Vector2, dt: f32) {
+        // only move once the point leaves the dead zone, and then just enough to bring it back
+        let zoom = self.zoom();
+        let (half_w, half_h) = (DEAD_ZONE_W * 0.5 / zoom, DEAD_ZONE_H * 0.5 / zoom);
+        self.goal.x = self.goal.x.clamp(point.x - half_w, point.x + half_w);
+        self.goal.y = self.goal.y.clamp(point.y - half_h, point.y + half_h);
+
+        // exponential ease, the same feel whatever the tick rate
+        let t = 1.0 - (-SMOOTHING * dt).exp();
+        let eased = Vector2::new(
+            self.target.x + (self.goal.x - self.target.x) * t,
+            self.target.y + (self.goal.y - self.target.y) * t,
+        );
+        self.target = self.clamped(eased);
+    }
+
+    // keeps the view inside the bounds. a map smaller than the screen sits in the middle
+    fn clamped(&self, point: Vector2) -> Vector2 {
+        let Some(bounds) = self.bounds else { return point };
+        let (half_w, half_h) = (self.screen_w * 0.5 / self.zoom(), self.screen_h * 0.5 / self.zoom());
+
+        let axis = |value: f32, start: f32, size: f32, half: f32| {
+            if size <= half * 2.0 {
+                start + size * 0.5
+            } else {
+                value.clamp(start + half, start + size - half)
+            }
+        };
+
+        Vector2::new(
+            axis(point.x, bounds.x, bounds.width, half_w),
+            axis(point.y, bounds.y, bounds.height, half_h),
+        )
+    }
+
+    /// For `begin_mode2D`. The target is snapped so world pixels land on
+    /// whole screen pixels, or the tiles would shimmer while scrolling.
+    pub fn camera2d(&self) -> Camera2D {
+        let zoom = self.zoom();
+        Camera2D {
+            offset: Vector2::new((self.screen_w * 0.5).round(), (self.screen_h * 0.5).round()),
+            target: Vector2::new((self.target.x * zoom).round() / zoom, (self.target.y * zoom).round() / zoom),
+            rotation: 0.0,
+            zoom,
+        }
+    }
+
+    /// Where a world point shows up on the (virtual) screen
+    pub fn world_to_screen(&self, point: Vector2) -> Vector2 {
+        let camera = self.camera2d();
+        Vector2::new(
+            (point.x - camera.target.x) * camera.zoom + camera.offset.x,
+            (point.y - camera.target.y) * camera.zoom + camera.offset.y,
+        )
+    }
+
+    /// Where the mouse (or any virtual screen point) is in the world
+    pub fn screen_to_world(&self, point: Vector2) -> Vector2 {
+        let camera = self.camera2d();
+        Vector2::new(
+            (point.x - camera.offset.x) / camera.zoom + camera.target.x,
+            (point.y - camera.offset.y) / camera.zoom + camera.target.y,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 60.0;
+
+    // 640x360 screen over a 1000x800 map
+    fn camera() -> GameCamera {
+        let mut camera = GameCamera::new(640.0, 360.0);
+        camera.set_bounds(Some(Rectangle::new(0.0, 0.0, 1000.0, 800.0)));
+        camera.snap_to(Vector2::new(500.0, 400.0));
+        camera
+    }
+
+    fn settle(camera: &mut GameCamera, point: Vector2) {
+        for _ in 0..600 {
+            camera.follow(point, TICK);
+        }
+    }
+
+    #[test]
+    fn small_moves_stay_inside_the_dead_zone() {
+        let mut camera = camera();
+        settle(&mut camera, Vector2::new(520.0, 412.0));
+        assert_eq!(camera.target, Vector2::new(500.0, 400.0));
+
+        // past the edge it moves just enough to bring the point back in
+        settle(&mut camera, Vector2::new(600.0, 400.0));
+        assert!((camera.target.x - (600.0 - DEAD_ZONE_W * 0.5)).abs() < 0.01, "{:?}", camera.target);
+        assert_eq!(camera.target.y, 400.0);
+    }
+
+    #[test]
+    fn following_eases_instead_of_jumping() {
+        let mut camera = camera();
+        camera.follow(Vector2::new(700.0, 400.0), TICK);
+        assert!(camera.target.x > 500.0 && camera.target.x < 650.0, "{:?}", camera.target);
+    }
+
+    #[test]
+    fn never_shows_past_the_edge_of_the_map() {
+        let mut camera = camera();
+        camera.snap_to(Vector2::new(0.0, 0.0));
+        assert_eq!(camera.target, Vector2::new(320.0, 180.0));
+
+        settle(&mut camera, Vector2::new(2000.0, 2000.0));
+        assert!((camera.target.x - 680.0).abs() < 0.01 && (camera.target.y - 620.0).abs() < 0.01, "{:?}", camera.target);
+
+        // roams freely without bounds
+        camera.set_bounds(None);
+        camera.snap_to(Vector2::new(-50.0, -50.0));
+        assert_eq!(camera.target, Vector2::new(-50.0, -50.0));
+    }
+
+    #[test]
+    fn a_map_smaller_than_the_view_sits_in_the_middle() {
+        let mut camera = GameCamera::new(640.0, 360.0);
+        camera.set_bounds(Some(Rectangle::new(16.0, 32.0, 320.0, 200.0)));
+        camera.snap_to(Vector2::new(0.0, 500.0));
+        assert_eq!(camera.target, Vector2::new(176.0, 132.0));
+
+        // zoomed in far enough, it fills the view and clamps again
+        camera.zoom_in();
+        camera.zoom_in();
+        camera.snap_to(Vector2::new(0.0, 500.0));
+        let (half_w, half_h) = (640.0 * 0.5 / 3.0, 360.0 * 0.5 / 3.0);
+        assert_eq!(camera.target, Vector2::new(16.0 + half_w, 232.0 - half_h));
+    }
+
+    #[test]
+    fn zooming_out_and_resizing_clamp_again() {
+        let mut camera = camera();
+        camera.zoom_in();
+        camera.snap_to(Vector2::new(1000.0, 800.0));
+        assert_eq!(camera.target, Vector2::new(840.0, 710.0));
+
+        camera.zoom_out();
+        assert_eq!(camera.target, Vector2::new(680.0, 620.0));
+
+        camera.set_screen_size(840.0, 360.0);
+        assert_eq!(camera.target, Vector2::new(580.0, 620.0));
+
+        // zoom stops at the ends
+        for _ in 0..10 {
+            camera.zoom_out();
+        }
+        assert_eq!(camera.zoom(), ZOOM_LEVELS[0]);
+    }
+
+    #[test]
+    fn screen_and_world_points_convert_both_ways() {
+        let mut camera = camera();
+        camera.zoom_in();
+        camera.snap_to(Vector2::new(500.3, 400.0));
+
+        // the snapped target is what's on screen, so that's what the centre shows
+        let centre = Vector2::new(320.0, 180.0);
+        assert_eq!(camera.screen_to_world(centre), Vector2::new(500.5, 400.0));
+        assert_eq!(camera.world_to_screen(Vector2::new(510.5, 390.0)), Vector2::new(340.0, 160.0));
+
+        for point in [Vector2::new(0.0, 0.0), Vector2::new(123.0, 45.0), Vector2::new(640.0, 360.0)] {
+            assert_eq!(camera.world_to_screen(camera.screen_to_world(point)), point);
+        }
+    }
+}
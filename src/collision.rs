@@ -0,0 +1,394 @@
+use crate::tmx::{self, Gid, TmxMap, TmxObject, TmxTileLayer};
+use raylib::prelude::{Rectangle, Vector2};
+use std::collections::HashSet;
+
+/// Custom property that makes a tile layer, object group, object or tileset
+/// tile block movement ("true") or not ("false"). Tile layers also take "base".
+pub const SOLID_PROPERTY: &str = "solid";
+
+/// How much of a tile layer blocks
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Solidity {
+    Open,
+    // every painted cell: walls, fences
+    Full,
+    // only the bottom tile of each column of painted cells, so trees and
+    // shelves block at their foot and can be walked behind
+    Base,
+}
+
+impl Solidity {
+    fn from_property(value: &str) -> Self {
+        match value {
+            "true" => Solidity::Full,
+            "base" => Solidity::Base,
+            _ => Solidity::Open,
+        }
+    }
+}
+
+// how far to look for an open spot when something spawns inside a wall, in tiles
+const FREE_SPOT_RADIUS: i32 = 24;
+
+/// Everything on a map that blocks movement, in map pixels.
+///
+/// Built once per map from, in order of precedence:
+/// - tiles drawn in Tiled's collision editor, which block with their shapes
+/// - tiles with a "solid" property, which block with their whole image
+/// - the painted cells of a solid tile layer (by name or "solid" property),
+///   all of them or just the bottom of each column
+/// - objects in a solid object group, or objects marked solid themselves
+pub struct CollisionMap {
+    tile_width: f32,
+    tile_height: f32,
+    // whole grid cells, the common case, looked up directly
+    cells: HashSet<(i32, i32)>,
+    // everything that doesn't line up with the grid
+    shapes: Vec<Rectangle>,
+}
+
+impl CollisionMap {
+    /// Nothing is solid. Used when a map has no layout to collide with.
+    pub fn empty(tile_width: f32, tile_height: f32) -> Self {
+        Self { tile_width, tile_height, cells: HashSet::new(), shapes: Vec::new() }
+    }
+
+    /// `solid_layers` pairs name patterns (see `tmx::name_matches`) with how
+    /// much of those layers blocks. The first match wins, and a layer's own
+    /// "solid" property wins over all of them. Object groups block whole
+    /// whenever they match.
+    pub fn from_tmx(tmx: &TmxMap, solid_layers: &[(&str, Solidity)]) -> Self {
+        let mut map = Self::empty(tmx.tile_width as f32, tmx.tile_height as f32);
+        let named = |name: &str| solid_layers.iter()
+            .find(|(pattern, _)| tmx::name_matches(pattern, name))
+            .map_or(Solidity::Open, |(_, solidity)| *solidity);
+
+        for layer in tmx.tile_layers() {
+            match layer.properties.get(SOLID_PROPERTY) {
+                Some(value) => {
+                    // an explicitly open layer ignores its tiles' shapes too
+                    let solidity = Solidity::from_property(value);
+                    if solidity != Solidity::Open {
+                        map.add_tile_layer(tmx, layer, solidity);
+                    }
+                },
+                None => map.add_tile_layer(tmx, layer, named(&layer.name)),
+            }
+        }
+
+        for group in tmx.object_groups() {
+            let solid = tmx::flag(&group.properties, SOLID_PROPERTY).unwrap_or_else(|| named(&group.name) != Solidity::Open);
+            for object in &group.objects {
+                if tmx::flag(&object.properties, SOLID_PROPERTY).unwrap_or(solid) {
+                    map.add_object(object, group.offset_x, group.offset_y);
+                }
+            }
+        }
+
+        map
+    }
+
+    fn add_tile_layer(&mut self, tmx: &TmxMap, layer: &TmxTileLayer, solidity: Solidity) {
+        let on_grid = layer.offset_x == 0.0 && layer.offset_y == 0.0;
+
+        let painted: HashSet<(i32, i32)> = match solidity {
+            Solidity::Base => layer.chunks.iter().flat_map(|chunk| {
+                chunk.tiles.iter().enumerate()
+                    .filter(|(_, gid)| !gid.is_empty())
+                    .map(move |(i, _)| (chunk.x + i as i32 % chunk.width, chunk.y + i as i32 / chunk.width))
+            }).collect(),
+            _ => HashSet::new(),
+        };
+
+        for chunk in &layer.chunks {
+            for (i, gid) in chunk.tiles.iter().enumerate() {
+                if gid.is_empty() {
+                    continue;
+                }
+                let column = chunk.x + i as i32 % chunk.width;
+                let row = chunk.y + i as i32 / chunk.width;
+                let solid = match solidity {
+                    Solidity::Open => false,
+                    Solidity::Full => true,
+                    Solidity::Base => !painted.contains(&(column, row + 1)),
+                };
+                // tiles hang up from the bottom-left of their cell, like when drawing
+                let bottom_left = Vector2::new(
+                    column as f32 * self.tile_width + layer.offset_x,
+                    (row + 1) as f32 * self.tile_height + layer.offset_y,
+                );
+
+                match tile_collision(tmx, *gid) {
+                    TileCollision::Shapes(shapes) => {
+                        self.shapes.extend(shapes.into_iter().map(|r| offset(r, bottom_left)));
+                    },
+                    TileCollision::Image(image) => self.shapes.push(offset(image, bottom_left)),
+                    TileCollision::Open => {},
+                    TileCollision::Layer if solid && on_grid => {
+                        self.cells.insert((column, row));
+                    },
+                    TileCollision::Layer if solid => {
+                        let cell = Rectangle::new(0.0, -self.tile_height, self.tile_width, self.tile_height);
+                        self.shapes.push(offset(cell, bottom_left));
+                    },
+                    TileCollision::Layer => {},
+                }
+            }
+        }
+    }
+
+    fn add_object(&mut self, object: &TmxObject, offset_x: f32, offset_y: f32) {
+        // points and polylines have no area to bump into. ellipses and
+        // polygons count as their bounding box
+        let (x, y, width, height) = object.bounds();
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        // tile objects are anchored at their bottom-left, everything else at the top-left
+        let top = if object.gid.is_some() { y - height } else { y };
+        self.shapes.push(Rectangle::new(x + offset_x, top + offset_y, width, height));
+    }
+
+    /// Whether anything solid overlaps `body`. Touching edges don't count,
+    /// so something pushed flush against a wall can still slide along it.
+    pub fn blocked(&self, body: Rectangle) -> bool {
+        let first_column = (body.x / self.tile_width).floor() as i32;
+        let last_column = ((body.x + body.width) / self.tile_width).ceil() as i32 - 1;
+        let first_row = (body.y / self.tile_height).floor() as i32;
+        let last_row = ((body.y + body.height) / self.tile_height).ceil() as i32 - 1;
+
+        for row in first_row..=last_row.max(first_row) {
+            for column in first_column..=last_column.max(first_column) {
+                if self.cells.contains(&(column, row)) {
+                    let cell = Rectangle::new(column as f32 * self.tile_width, row as f32 * self.tile_height, self.tile_width, self.tile_height);
+                    if overlaps(body, cell) {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        self.shapes.iter().any(|shape| overlaps(body, *shape))
+    }
+
+    /// How far `body` can actually go when it tries to move by `delta`.
+    /// Each axis is tried on its own, so running diagonally into a wall slides
+    /// along it instead of stopping dead. Something that starts out stuck
+    /// inside a wall moves freely until it's out.
+    pub fn move_and_slide(&self, body: Rectangle, delta: Vector2) -> Vector2 {
+        if self.blocked(body) {
+            return delta;
+        }
+
+        // small steps, so nothing skips through a wall thinner than a fast move
+        let step_size = self.tile_width.min(self.tile_height) * 0.25;
+        let steps = (delta.x.abs().max(delta.y.abs()) / step_size).ceil().max(1.0) as i32;
+        let step = Vector2::new(delta.x / steps as f32, delta.y / steps as f32);
+
+        let mut moved = Vector2::new(0.0, 0.0);
+        let mut at = body;
+        for _ in 0..steps {
+            let dx = self.furthest(at, Vector2::new(step.x, 0.0));
+            at.x += dx;
+            let dy = self.furthest(at, Vector2::new(0.0, step.y));
+            at.y += dy;
+
+            moved.x += dx;
+            moved.y += dy;
+            if dx == 0.0 && dy == 0.0 {
+                break;
+            }
+        }
+
+        moved
+    }
+
+    // the longest part of a single-axis step that stays clear, found by halving
+    fn furthest(&self, body: Rectangle, step: Vector2) -> f32 {
+        let mut fraction = 1.0;
+        for _ in 0..5 {
+            let to = Rectangle::new(body.x + step.x * fraction, body.y + step.y * fraction, body.width, body.height);
+            if !self.blocked(to) {
+                return (step.x + step.y) * fraction;
+            }
+            fraction *= 0.5;
+        }
+        0.0
+    }
+
+    /// The closest place to `around` where `body_at(position)` doesn't
+    /// overlap anything, searching outwards half a tile at a time
+    pub fn find_free(&self, around: Vector2, body_at: impl Fn(Vector2) -> Rectangle) -> Option<Vector2> {
+        let (step_x, step_y) = (self.tile_width * 0.5, self.tile_height * 0.5);
+
+        for radius in 0..=FREE_SPOT_RADIUS * 2 {
+            // the ring of candidates `radius` steps away, nearest first
+            let mut ring: Vec<Vector2> = Vec::new();
+            for j in -radius..=radius {
+                for i in -radius..=radius {
+                    if i.abs() == radius || j.abs() == radius {
+                        ring.push(Vector2::new(around.x + i as f32 * step_x, around.y + j as f32 * step_y));
+                    }
+                }
+            }
+            ring.sort_by(|a, b| distance_sq(*a, around).total_cmp(&distance_sq(*b, around)));
+
+            if let Some(spot) = ring.into_iter().find(|spot| !self.blocked(body_at(*spot))) {
+                return Some(spot);
+            }
+        }
+
+        None
+    }
+}
+
+enum TileCollision {
+    // collision editor shapes, relative to the tile's bottom-left
+    Shapes(Vec<Rectangle>),
+    // "solid" tile: its whole image, relative to the bottom-left
+    Image(Rectangle),
+    // "solid" = false on the tile
+    Open,
+    // up to the layer
+    Layer,
+}
+
+fn tile_collision(tmx: &TmxMap, gid: Gid) -> TileCollision {
+    let Some((_, tileset)) = tmx.tileset_for(gid) else { return TileCollision::Layer };
+    let local_id = gid.id() - tileset.first_gid;
+    let (width, height) = (tileset.tile_width as f32, tileset.tile_height as f32);
+
+    if let Some(shapes) = tileset.tile_shapes.get(&local_id) {
+        let rects = shapes.iter()
+            .map(|shape| shape.bounds())
+            .filter(|&(_, _, w, h)| w > 0.0 && h > 0.0)
+            .map(|(x, y, w, h)| {
+                // mirror with the tile. diagonal flips keep the unflipped shape
+                let x = if gid.flipped_horizontally() { width - x - w } else { x };
+                let y = if gid.flipped_vertically() { height - y - h } else { y };
+                Rectangle::new(x, y - height, w, h)
+            })
+            .collect();
+        return TileCollision::Shapes(rects);
+    }
+
+    match tileset.tile_properties.get(&local_id).and_then(|props| tmx::flag(props, SOLID_PROPERTY)) {
+        Some(true) => TileCollision::Image(Rectangle::new(0.0, -height, width, height)),
+        Some(false) => TileCollision::Open,
+        None => TileCollision::Layer,
+    }
+}
+
+fn offset(rect: Rectangle, by: Vector2) -> Rectangle {
+    Rectangle::new(rect.x + by.x, rect.y + by.y, rect.width, rect.height)
+}
+
+fn overlaps(a: Rectangle, b: Rectangle) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+fn distance_sq(a: Vector2, b: Vector2) -> f32 {
+    (a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    // 16px tiles: a wall down column 3, a tree three tiles tall in column 1,
+    // and a triangle of rocks drawn as a polygon
+    const MAP: &str = r#"<map orientation="orthogonal" tilewidth="16" tileheight="16" infinite="0">
+        <layer id="1" name="Walls" width="6" height="6"><data encoding="csv">
+            0,0,0,1,0,0,
+            0,0,0,1,0,0,
+            0,0,0,1,0,0,
+            0,0,0,1,0,0,
+            0,0,0,1,0,0,
+            0,0,0,1,0,0
+        </data></layer>
+        <layer id="2" name="Trees" width="6" height="6"><data encoding="csv">
+            0,0,0,0,0,0,
+            0,2,0,0,0,0,
+            0,2,0,0,0,0,
+            0,2,0,0,0,0,
+            0,0,0,0,0,0,
+            0,0,0,0,0,0
+        </data></layer>
+        <objectgroup id="3" name="Rocks">
+            <properties><property name="solid" type="bool" value="true"/></properties>
+            <object id="1" x="64" y="64"><polygon points="0,0 32,0 16,16"/></object>
+            <object id="2" x="64" y="0"><polyline points="0,0 32,32"/></object>
+        </objectgroup>
+    </map>"#;
+
+    const LAYERS: [(&str, Solidity); 2] = [("Walls", Solidity::Full), ("Trees", Solidity::Base)];
+
+    fn collision() -> CollisionMap {
+        CollisionMap::from_tmx(&TmxMap::parse(MAP, Path::new("")).unwrap(), &LAYERS)
+    }
+
+    fn cell(column: i32, row: i32) -> Rectangle {
+        Rectangle::new(column as f32 * 16.0 + 4.0, row as f32 * 16.0 + 4.0, 8.0, 8.0)
+    }
+
+    #[test]
+    fn base_layers_only_block_at_the_foot() {
+        let map = collision();
+        assert!(!map.blocked(cell(1, 1)));
+        assert!(!map.blocked(cell(1, 2)));
+        assert!(map.blocked(cell(1, 3)));
+        assert!(map.blocked(cell(3, 0)) && map.blocked(cell(3, 5)));
+    }
+
+    #[test]
+    fn polygons_block_with_the_box_around_their_corners() {
+        let map = collision();
+        assert!(map.blocked(Rectangle::new(90.0, 70.0, 2.0, 2.0)));
+        assert!(!map.blocked(Rectangle::new(90.0, 82.0, 2.0, 2.0)));
+        assert!(!map.blocked(Rectangle::new(98.0, 70.0, 2.0, 2.0)));
+        // a polyline has nothing to bump into
+        assert!(!map.blocked(Rectangle::new(78.0, 14.0, 2.0, 2.0)));
+    }
+
+    #[test]
+    fn moving_diagonally_into_a_wall_slides_along_it() {
+        let map = collision();
+        let body = Rectangle::new(36.0, 20.0, 8.0, 8.0);
+
+        // stops flush with the wall at x 48, keeps going down
+        let moved = map.move_and_slide(body, Vector2::new(20.0, 10.0));
+        assert_eq!(moved, Vector2::new(4.0, 10.0));
+        assert!(!map.blocked(Rectangle::new(body.x + moved.x, body.y + moved.y, 8.0, 8.0)));
+    }
+
+    #[test]
+    fn fast_moves_dont_skip_through_walls() {
+        let map = collision();
+        let moved = map.move_and_slide(Rectangle::new(36.0, 20.0, 8.0, 8.0), Vector2::new(200.0, 0.0));
+        assert_eq!(moved.x, 4.0);
+    }
+
+    #[test]
+    fn something_stuck_in_a_wall_can_move_out() {
+        let map = collision();
+        let stuck = Rectangle::new(50.0, 20.0, 8.0, 8.0);
+        assert!(map.blocked(stuck));
+        assert_eq!(map.move_and_slide(stuck, Vector2::new(12.0, 0.0)), Vector2::new(12.0, 0.0));
+    }
+
+    #[test]
+    fn find_free_picks_the_nearest_open_spot() {
+        let map = collision();
+        let body_at = |at: Vector2| Rectangle::new(at.x - 4.0, at.y - 8.0, 8.0, 8.0);
+
+        let open = Vector2::new(20.0, 40.0);
+        assert_eq!(map.find_free(open, body_at), Some(open));
+
+        // in the middle of the wall, it's half a tile either way
+        let spot = map.find_free(Vector2::new(56.0, 40.0), body_at).unwrap();
+        assert!(!map.blocked(body_at(spot)));
+        assert_eq!(spot.y, 40.0);
+        assert!(spot.x == 40.0 || spot.x == 72.0, "{:?}", spot);
+    }
+}
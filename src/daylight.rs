@@ -0,0 +1,183 @@
+use crate::draw::DrawList;
+use crate::tilemap::TileMap;
+use crate::tmx::{self, TmxTileLayer};
+use raylib::prelude::{BlendMode, Vector2};
+use raylib::color::Color;
+
+/// Tiled layer property that makes a layer light up at night ("true") or
+/// not ("false"), whatever its name
+pub const GLOW_PROPERTY: &str = "glow";
+
+// lamps are on indoors, so the house only gets this much of the outside light
+const INDOOR_SHARE: f32 = 0.4;
+// lit windows seen from the yard, and the moon through them from inside
+const LAMPLIGHT: Color = Color::new(255, 190, 110, 255);
+const MOONLIGHT: Color = Color::new(120, 150, 230, 255);
+
+const NIGHT: Color = Color::new(70, 80, 150, 255);
+const DAWN: Color = Color::new(235, 175, 160, 255);
+const DAY: Color = Color::WHITE;
+const DUSK: Color = Color::new(240, 150, 110, 255);
+
+// (hour, tint, darkness) around the clock. the light blends from one to the next
+const KEYFRAMES: [(f32, Color, f32); 8] = [
+    (0.0, NIGHT, 1.0),
+    (5.0, NIGHT, 1.0),
+    (6.5, DAWN, 0.5),
+    (8.0, DAY, 0.0),
+    (17.5, DAY, 0.0),
+    (19.0, DUSK, 0.5),
+    (20.5, NIGHT, 1.0),
+    (24.0, NIGHT, 1.0),
+];
+
+/// Rough part of the day, for anything that doesn't need the exact light
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DayPhase {
+    Night,
+    Dawn,
+    Day,
+    Dusk,
+}
+
+impl DayPhase {
+    pub fn at(hour: f32) -> Self {
+        match hour.rem_euclid(24.0) {
+            h if h < 5.0 => DayPhase::Night,
+            h if h < 8.0 => DayPhase::Dawn,
+            h if h < 17.5 => DayPhase::Day,
+            h if h < 20.5 => DayPhase::Dusk,
+            _ => DayPhase::Night,
+        }
+    }
+}
+
+/// The light in a scene at one time of day
+#[derive(Clone, Copy, Debug)]
+pub struct Daylight {
+    // multiplied over the world
+    pub tint: Color,
+    // outside, whatever the lamps are doing. 0 in full daylight, 1 in the middle of the night
+    pub darkness: f32,
+    outdoors: bool,
+}
+
+impl Daylight {
+    /// `hour` is 0 to 24 on the game clock
+    pub fn at(hour: f32, outdoors: bool) -> Self {
+        let hour = hour.rem_euclid(24.0);
+        let next = KEYFRAMES.iter().position(|(h, _, _)| *h > hour).unwrap_or(KEYFRAMES.len() - 1);
+        let (from_hour, from_tint, from_dark) = KEYFRAMES[next - 1];
+        let (to_hour, to_tint, to_dark) = KEYFRAMES[next];
+        let t = ((hour - from_hour) / (to_hour - from_hour)).clamp(0.0, 1.0);
+
+        let mut tint = from_tint.lerp(to_tint, t);
+        let darkness = from_dark + (to_dark - from_dark) * t;
+        if !outdoors {
+            tint = Color::WHITE.lerp(tint, INDOOR_SHARE);
+        }
+
+        Self { tint, darkness, outdoors }
+    }
+
+    /// Tints everything drawn so far. Call after the world and before the UI.
+    pub fn draw(&self, d: &mut DrawList, screen_w: f32, screen_h: f32) {
+        // nothing to do in broad daylight
+        if (self.tint.r, self.tint.g, self.tint.b) == (255, 255, 255) {
+            return;
+        }
+        let mut d = d.begin_blend(BlendMode::BLEND_MULTIPLIED);
+        d.draw_rectangle(0, 0, screen_w as i32, screen_h as i32, self.tint);
+    }
+
+    /// Lights up the window layers as it gets dark. Call inside the
+    /// camera's 2D mode, after `draw`, so the glow isn't darkened too.
+    pub fn draw_glow(&self, d: &mut DrawList, map: &TileMap, glowing: &[&str]) {
+        // only once it's properly dark, not the whole afternoon
+        let strength = ((self.darkness - 0.3) / 0.7).clamp(0.0, 1.0);
+        if strength <= 0.0 {
+            return;
+        }
+
+        let color = if self.outdoors { LAMPLIGHT } else { MOONLIGHT };
+        let tint = Color::BLACK.lerp(color, strength);
+
+        let mut d = d.begin_blend(BlendMode::BLEND_ADDITIVE);
+        for layer in map.tmx.tile_layers().filter(|layer| glows(layer, glowing)) {
+            for (gid, bottom_left) in map.layer_tiles(layer, Vector2::new(0.0, 0.0), 1.0) {
+                map.draw_tile(&mut d, gid, bottom_left, 1.0, tint);
+            }
+        }
+    }
+}
+
+/// Whether a layer glows at night: its "glow" property, otherwise whether
+/// its name matches one of `patterns` (see `tmx::name_matches`)
+pub fn glows(layer: &TmxTileLayer, patterns: &[&str]) -> bool {
+    tmx::flag(&layer.properties, GLOW_PROPERTY)
+        .unwrap_or_else(|| patterns.iter().any(|pattern| tmx::name_matches(pattern, &layer.name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(color: Color) -> (u8, u8, u8) {
+        (color.r, color.g, color.b)
+    }
+
+    #[test]
+    fn phases_change_at_the_keyframes() {
+        let phases = [
+            (0.0, DayPhase::Night),
+            (4.99, DayPhase::Night),
+            (5.0, DayPhase::Dawn),
+            (7.99, DayPhase::Dawn),
+            (8.0, DayPhase::Day),
+            (17.49, DayPhase::Day),
+            (17.5, DayPhase::Dusk),
+            (20.49, DayPhase::Dusk),
+            (20.5, DayPhase::Night),
+            (23.99, DayPhase::Night),
+            (24.0, DayPhase::Night),
+            (-1.0, DayPhase::Night),
+            (32.0, DayPhase::Day),
+        ];
+        for (hour, phase) in phases {
+            assert_eq!(DayPhase::at(hour), phase, "at {}", hour);
+        }
+    }
+
+    #[test]
+    fn light_holds_at_the_keyframes() {
+        for hour in [0.0, 5.0, 20.5, 24.0] {
+            let night = Daylight::at(hour, true);
+            assert_eq!((rgb(night.tint), night.darkness), (rgb(NIGHT), 1.0), "at {}", hour);
+        }
+        for hour in [8.0, 12.0, 17.5] {
+            let day = Daylight::at(hour, true);
+            assert_eq!((rgb(day.tint), day.darkness), (rgb(DAY), 0.0), "at {}", hour);
+        }
+        assert_eq!(rgb(Daylight::at(6.5, true).tint), rgb(DAWN));
+        assert_eq!(rgb(Daylight::at(19.0, true).tint), rgb(DUSK));
+    }
+
+    #[test]
+    fn light_blends_between_keyframes() {
+        // halfway from night at 5 to dawn at 6.5
+        let early = Daylight::at(5.75, true);
+        assert_eq!(early.darkness, 0.75);
+        assert!(early.tint.r > NIGHT.r && early.tint.r < DAWN.r);
+
+        // the clock wraps, and a little before midnight is the same night as after it
+        assert_eq!(Daylight::at(23.5, true).darkness, Daylight::at(-0.5, true).darkness);
+    }
+
+    #[test]
+    fn indoors_only_gets_some_of_the_outside_light() {
+        let inside = Daylight::at(0.0, false);
+        assert_eq!(inside.darkness, 1.0);
+        assert!(inside.tint.r > NIGHT.r && inside.tint.r < 255);
+        assert_eq!(rgb(Daylight::at(12.0, false).tint), rgb(DAY));
+    }
+}
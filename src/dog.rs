@@ -1,11 +1,26 @@
 
 use crate::types::{Food, Gender, Percent, DrainRate};
-use crate::animation::{Facing, Pose, Emotion, AnimationPlayer, AnimationKey, AnimationBank};
+use crate::animation::{Facing, Pose, Emotion, AnimationKey, AnimationBank};
+use crate::animator::{Animator, AnimationSignal, OneShotPriority};
+use crate::assets::Assets;
+use crate::breeding::{Lineage, Puppy};
+use crate::collision::CollisionMap;
+use crate::draw::DrawList;
+use crate::render::Sprite;
+use crate::state_machine::{AnimationStateMachine, Conditions, GraphStep};
+use crate::genetics::{Coat, DogSize, Genome, Personality};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Instant, Duration};
-use raylib::prelude::{RaylibDraw, RaylibDrawHandle, Rectangle, Vector2};
+use raylib::prelude::{Rectangle, Vector2};
+use raylib::color::Color;
 use raylib::{RaylibHandle, RaylibThread};
 
+// how much ground a dog stands on, in map pixels
+const FOOTPRINT_W: f32 = 14.0;
+const FOOTPRINT_H: f32 = 6.0;
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum DogBreed {
     Pitbull,
     GermanShepherd,
@@ -33,25 +48,56 @@ pub enum DogBreed {
     Dalmatian,
 }
 
+impl DogBreed {
+    pub const ALL: [DogBreed; 24] = [
+        DogBreed::Pitbull, DogBreed::GermanShepherd, DogBreed::Corgie, DogBreed::AustralianShepherd,
+        DogBreed::BorderCollie, DogBreed::BloodHound, DogBreed::CavalierKingCharlesSpaniel, DogBreed::Havanese,
+        DogBreed::Husky, DogBreed::Mutt, DogBreed::Poodle, DogBreed::Labradoodle,
+        DogBreed::Goldendoodle, DogBreed::Shepadoodle, DogBreed::Cockapoo, DogBreed::Schnauser,
+        DogBreed::ScottishTerrier, DogBreed::Labrador, DogBreed::GoldenRetriever, DogBreed::SaintBernard,
+        DogBreed::Greyhound, DogBreed::GreatDane, DogBreed::Mastiff, DogBreed::Dalmatian,
+    ];
+}
+
+/// Unique per dog for the whole run, used by lineage and the pedigree
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct DogId(pub u64);
+
+static NEXT_DOG_ID: AtomicU64 = AtomicU64::new(1);
+
+impl DogId {
+    fn next() -> Self {
+        Self(NEXT_DOG_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 
 pub struct Dog {
+    pub id: DogId,
     pub name: String,
     pub breed: DogBreed,
     pub gender: Gender,
     pub date_of_birth: chrono::NaiveDate,
 
+    pub genome: Genome,
+    pub lineage: Lineage,
+
     pub position: Vector2,
     pub velocity: Vector2,
 
     pub facing: Facing,
     pub pose: Pose,
     pub emotion: Emotion,
+    // where the player asked the dog to be. the state machine gets there
+    // through transition clips, so `pose` lags behind for a moment
+    pub requested_pose: Pose,
+    state_machine: AnimationStateMachine,
 
-    // shared animation descriptors live in a map filled at load time
-    pub animations: AnimationBank,
+    // shared animation descriptors, one bank for every dog using the same sprites
+    pub animations: Arc<AnimationBank>,
 
-    // the per-dog player that uses the current animation descripttor
-    pub sprite_player: Option<AnimationPlayer>,
+    // the per-dog base clip + one-shots playing over it
+    pub animator: Option<Animator>,
 
     pub food_level: Percent,
     pub water_level: Percent,
@@ -60,63 +106,97 @@ pub struct Dog {
     pub social_battery: Percent,
     pub energy_level: Percent,
     pub health_level: Percent,
+    // 0 is clean. rain and wet ground build it up, it dries and flakes off in the dry
+    pub mud_level: Percent,
+    // 0 is calm. spikes with a fright and settles back down by itself
+    pub anxiety_level: Percent,
 
     last_drain_applied: Instant,
     pub food_drain_rate: DrainRate,
     pub water_drain_rate: DrainRate,
+    // awake in daylight. the dark makes it up to 3x faster
+    pub energy_drain_rate: DrainRate,
+    // standing in a downpour. walking doubles it
+    pub mud_rate: DrainRate,
+    pub mud_dry_rate: DrainRate,
+    pub calm_down_rate: DrainRate,
+}
+
+/// What the world around a dog is doing to it, for `Dog::apply_drains`
+#[derive(Clone, Copy, Debug)]
+pub struct Surroundings {
+    // how dark it is outside, 0 to 1 (see `Daylight`)
+    pub darkness: f32,
+    // how much faster than usual the dog gets thirsty
+    pub thirst: f64,
+    // how wet the ground is, 0 to 1
+    pub wetness: f32,
+}
+
+impl Default for Surroundings {
+    fn default() -> Self {
+        Self { darkness: 0.0, thirst: 1.0, wetness: 0.0 }
+    }
 }
 
+// most a fright can add to anxiety, for the least bold dog
+const MAX_FRIGHT: f64 = 15.0;
+// how much a pat helps, each time
+const PETTING: f64 = 5.0;
+const MUD_COLOR: Color = Color::new(120, 85, 50, 255);
+
 const ONE_HOUR: Duration = Duration::from_secs(3600);
+const ONE_MINUTE: Duration = Duration::from_secs(60);
+const DOG_MANIFEST: &str = "images/pixeldoggies/manifest.json";
 
 impl Dog {
-    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, name: String, breed: DogBreed, gender: Gender, date_of_birth: chrono::NaiveDate) -> Self {
-        let mut animations = AnimationBank::new();
-
-        match breed {
-            DogBreed::Dalmatian => {
-                let _ = animations.insert_strip(
-                    rl, thread,
-                    AnimationKey { pose: Pose::Standing, emotion: Emotion::Neutral, facing: Facing::Right },
-                    "images/pixeldoggies/AttackDog.png", 16, 0.10, true);
-
-                let _ = animations.insert_strip(
-                    rl, thread,
-                    AnimationKey { pose: Pose::Standing, emotion: Emotion::Neutral, facing: Facing::Right },
-                    "images/pixeldoggies/BarkDog.png", 12, 0.10, true);
-
-                let _ = animations.insert_strip(
-                    rl, thread,
-                    AnimationKey { pose: Pose::Standing, emotion: Emotion::Neutral, facing: Facing::Right },
-                    "images/pixeldoggies/IdleDog.png", 7, 0.10, true);
-
-                let _ = animations.insert_strip(
-                    rl, thread,
-                    AnimationKey { pose: Pose::Standing, emotion: Emotion::Neutral, facing: Facing::Right },
-                    "images/pixeldoggies/LieDown.png", 12, 0.10, true);
-
-                let _ = animations.insert_strip(
-                    rl, thread,
-                    AnimationKey { pose: Pose::Walking, emotion: Emotion::Neutral, facing: Facing::Right },
-                    "images/pixeldoggies/RunDog.png", 5, 0.10, true);
+    pub fn new(assets: &mut Assets, rl: &mut RaylibHandle, thread: &RaylibThread, name: String, breed: DogBreed, gender: Gender, date_of_birth: chrono::NaiveDate) -> Self {
+        let animations = Self::load_animations(assets, rl, thread);
+        Self::with_animations(name, breed, gender, date_of_birth, Genome::for_breed(breed), Lineage::founder(), animations)
+    }
 
-            },
-            _ => {
-                // panic... unsupported breed
-            },
-        }
+    /// Give a newborn from `breeding::whelp_litter` a name and a body,
+    /// drawn with `animations` (its parents', usually)
+    pub fn from_puppy(name: String, puppy: Puppy, date_of_birth: chrono::NaiveDate, animations: Arc<AnimationBank>) -> Self {
+        Self::with_animations(name, puppy.breed, puppy.gender, date_of_birth, puppy.genome, puppy.lineage, animations)
+    }
+
+    /// The sprites every dog is drawn with, loaded once and shared
+    pub fn load_animations(assets: &mut Assets, rl: &mut RaylibHandle, thread: &RaylibThread) -> Arc<AnimationBank> {
+        // the pixeldoggies sheets are the only dog sprites we have so far, so every
+        // breed (shelter dogs, puppies) borrows them until breed-specific art exists.
+        // clips, timings, events and pose bindings all live in the manifest
+        assets.animations_or_placeholder(rl, thread, DOG_MANIFEST)
+    }
 
+    /// A dog drawn with `animations`, which are already loaded
+    pub fn with_animations(
+        name: String,
+        breed: DogBreed,
+        gender: Gender,
+        date_of_birth: chrono::NaiveDate,
+        genome: Genome,
+        lineage: Lineage,
+        animations: Arc<AnimationBank>,
+    ) -> Self {
         let mut dog = Self {
+            id: DogId::next(),
             name: name,
             breed: breed,
             gender: gender,
             date_of_birth: date_of_birth,
 
+            genome: genome,
+            lineage: lineage,
+
             animations: animations,
-            sprite_player: None,
+            animator: None,
 
             emotion: Emotion::Neutral,
             pose: Pose::Standing,
             facing: Facing::Right,
+            requested_pose: Pose::Standing,
+            state_machine: AnimationStateMachine::new(),
 
             // position and evelocity
             position: Vector2 { x: 0.0, y: 0.0 },
@@ -130,24 +210,44 @@ impl Dog {
             social_battery: Percent::new(50.0),
             energy_level: Percent::new(50.0),
             health_level: Percent::new(100.0),
+            mud_level: Percent::new(0.0),
+            anxiety_level: Percent::new(0.0),
 
             // init drain rates
             food_drain_rate: DrainRate::new(Percent::new(10.0), ONE_HOUR), // 10% food consumption per hour
             water_drain_rate: DrainRate::new(Percent::new(20.0), ONE_HOUR), // 20% water consumption per hour
+            energy_drain_rate: DrainRate::new(Percent::new(10.0), ONE_HOUR), // 10% tiredness per hour
+            mud_rate: DrainRate::new(Percent::new(120.0), ONE_HOUR), // 120% muddier per hour in a downpour
+            mud_dry_rate: DrainRate::new(Percent::new(30.0), ONE_HOUR), // 30% cleaner per hour on dry ground
+            calm_down_rate: DrainRate::new(Percent::new(20.0), ONE_MINUTE), // 20% calmer per minute
 
             last_drain_applied: Instant::now(),
         };
 
-        dog.init_sprite_player();
+        dog.init_animator();
 
         return dog;
     }
 
+    pub fn coat(&self) -> Coat {
+        self.genome.coat()
+    }
+
+    pub fn size(&self) -> DogSize {
+        self.genome.size()
+    }
+
+    pub fn personality(&self) -> &Personality {
+        &self.genome.personality
+    }
+
     pub fn feed(&mut self, food: &Food) {
         self.food_level.increase(food.nutritional_value.clone());
     }
 
-    pub fn apply_drains(&mut self) {
+    /// Dogs tire faster at night and sleep back their energy best then too,
+    /// get thirsty faster in the heat and muddy in the wet.
+    pub fn apply_drains(&mut self, surroundings: &Surroundings) {
         // don't do this more than once per second
         let elapsed = self.last_drain_applied.elapsed().as_secs_f64();
         if elapsed >= 1.0 {
@@ -156,42 +256,108 @@ impl Dog {
             self.food_level.decrease(base_food_drain);
 
             // drain water
-            let base_water_drain = calculate_base_drain(&self.water_drain_rate, elapsed);
+            let base_water_drain = calculate_base_drain(&self.water_drain_rate, elapsed * surroundings.thirst);
             self.water_level.decrease(base_water_drain);
 
             // drain bladder comfort 
             // drain digestion comfort
             // drain social battery
-            // drain energy level
+
+            // drain energy level, or sleep it back. the dark makes both go faster
+            let sleepiness = 1.0 + 2.0 * f64::from(surroundings.darkness.clamp(0.0, 1.0));
+            if self.pose == Pose::Sleeping {
+                self.energy_level.increase(calculate_base_drain(&self.energy_drain_rate, elapsed * 2.0 * sleepiness));
+            } else {
+                self.energy_level.decrease(calculate_base_drain(&self.energy_drain_rate, elapsed * sleepiness));
+            }
+
             // drain health level
 
+            // get muddy in the wet, faster on the move, and dry off out of it
+            let wetness = f64::from(surroundings.wetness.clamp(0.0, 1.0));
+            if wetness > 0.0 {
+                let walking = if self.pose == Pose::Walking { 2.0 } else { 1.0 };
+                self.mud_level.increase(calculate_base_drain(&self.mud_rate, elapsed * wetness * walking));
+            } else {
+                self.mud_level.decrease(calculate_base_drain(&self.mud_dry_rate, elapsed));
+            }
+
+            // settle down after a fright
+            self.anxiety_level.decrease(calculate_base_drain(&self.calm_down_rate, elapsed));
+
             // update last drain time
             self.last_drain_applied = Instant::now();
         }
     }
 
+    /// A scratch behind the ears cheers a dog up and calms it down
+    pub fn pet(&mut self) {
+        self.social_battery.increase(Percent::new(PETTING));
+        self.anxiety_level.decrease(Percent::new(PETTING));
+    }
+
+    /// A sudden fright, like thunder. Bold dogs shrug most of it off, but
+    /// every dog flinches.
+    pub fn startle(&mut self) {
+        let timidness = 1.0 - f64::from(self.personality().boldness.min(100)) / 100.0;
+        self.anxiety_level.increase(Percent::new(MAX_FRIGHT * (0.25 + 0.75 * timidness)));
+        self.play_clip("hurt", OneShotPriority::High);
+    }
+
     // call this once after loading descriptors
-    pub fn init_sprite_player(&mut self) {
+    pub fn init_animator(&mut self) {
         let key = AnimationKey { pose: self.pose, emotion: self.emotion, facing: self.facing };
-        if let Some(descriptor) = self.animations.get(&key) {
-            // clone desc into player (desc contains the texture2D so adjust as needed)
-            self.sprite_player = Some(AnimationPlayer::new(descriptor.clone()));
+        if let Some(found) = self.animations.resolve(&key) {
+            // clone desc into the animator (desc contains the texture2D so adjust as needed)
+            let mut animator = Animator::new(found.descriptor.clone(), found.mirrored);
+            animator.set_one_shots_mirrored(self.facing == Facing::Left);
+            self.animator = Some(animator);
+        }
+    }
+
+    /// Play a named clip ("bark", "attack", ...) once over the pose animation.
+    /// `update` reports AnimationSignal::Finished when it's done.
+    pub fn play_clip(&mut self, name: &str, priority: OneShotPriority) {
+        if let (Some(descriptor), Some(animator)) = (self.animations.clip(name), &mut self.animator) {
+            animator.play_once(name, descriptor.clone(), priority);
+        }
+    }
+
+    /// Sit, lie down, stand up... The dog plays whatever transition clips the
+    /// sprite manifest declares on the way.
+    pub fn request_pose(&mut self, pose: Pose) {
+        self.requested_pose = pose;
+    }
+
+    fn play_transition(&mut self, clip: &str, reversed: bool) {
+        match (self.animations.clip(clip), &mut self.animator) {
+            (Some(descriptor), Some(animator)) => {
+                let mirrored = self.facing == Facing::Left && descriptor.mirrorable;
+                animator.play_transition(descriptor.clone(), reversed, mirrored);
+            },
+            _ => {
+                // nothing to show, skip straight to the end
+                if let Some(pose) = self.state_machine.finish_transition() {
+                    self.set_visual_state(pose, self.emotion, self.facing);
+                }
+            }
         }
     }
 
     // call when visual state changes to switch animations
     pub fn set_visual_state(&mut self, pose: Pose, emotion: Emotion, facing: Facing) {
         let key = AnimationKey { pose, emotion, facing };
-        if let Some(descriptor) = self.animations.get(&key) {
-            match &mut self.sprite_player {
-                Some(player) => {
-                    // if different descriptor, replace/reset
-                    // here we simply compare pointer/texture or replace unconditionally
-                    player.descriptor = descriptor.clone();
-                    player.reset();
+        if let Some(found) = self.animations.resolve(&key) {
+            match &mut self.animator {
+                Some(animator) => {
+                    // the same clip (only the facing changed) carries on from its current frame
+                    animator.set_base(found.descriptor.clone(), found.mirrored);
+                    animator.set_one_shots_mirrored(facing == Facing::Left);
                 },
                 None => {
-                    self.sprite_player = Some(AnimationPlayer::new(descriptor.clone()));
+                    let mut animator = Animator::new(found.descriptor.clone(), found.mirrored);
+                    animator.set_one_shots_mirrored(facing == Facing::Left);
+                    self.animator = Some(animator);
                 }
             }
         }
@@ -200,16 +366,28 @@ impl Dog {
         self.facing = facing;
     }
 
-    // update movement + sprite each frame
-    // dt in seconds
-    pub fn update(&mut self, dt: f32) {
-        // basic movement example
-        self.position.x += self.velocity.x * dt;
-        self.position.y += self.velocity.y * dt;
+    /// The patch of ground the dog takes up around its feet, in map pixels.
+    /// Only the feet collide, so the head can overlap a wall it's walking along.
+    pub fn body_at(position: Vector2) -> Rectangle {
+        Rectangle::new(position.x - FOOTPRINT_W * 0.5, position.y - FOOTPRINT_H, FOOTPRINT_W, FOOTPRINT_H)
+    }
 
-        // select pose by velocity
-        let moving = (self.velocity.x.abs() + self.velocity.y.abs()) > 0.01;
-        let pose = if moving { Pose::Walking } else { self.pose }; // retain existing pose unless movement detected
+    pub fn body(&self) -> Rectangle {
+        Self::body_at(self.position)
+    }
+
+    // update movement + sprite each frame
+    // dt in seconds. returns what the animations did: frame events ("footstep", "bark", ...)
+    // and one-shots that finished
+    pub fn update(&mut self, dt: f32, collision: &CollisionMap) -> Vec<AnimationSignal> {
+        // walk as far as the walls allow, sliding along them
+        let wanted = Vector2::new(self.velocity.x * dt, self.velocity.y * dt);
+        let moved = collision.move_and_slide(self.body(), wanted);
+        self.position.x += moved.x;
+        self.position.y += moved.y;
+
+        // pushing against a wall isn't walking
+        let moving = dt > 0.0 && (moved.x.abs() + moved.y.abs()) / dt > 0.01;
 
         // facing from velocity.x if moving
         let facing = if self.velocity.x > 0.0 { Facing::Right }
@@ -219,39 +397,53 @@ impl Dog {
                      else if self.velocity.y < 0.0 { Facing::Front }
                      else { self.facing };
 
-        // update animation if needed
-        if pose != self.pose || facing != self.facing {
-            self.set_visual_state(pose, self.emotion, facing);
+        // walk whenever we're moving, otherwise head for whatever pose was asked for
+        let target = if moving { Pose::Walking } else { self.requested_pose };
+        let conditions = Conditions { moving };
+
+        match self.state_machine.step(&self.animations.graph, self.pose, target, &conditions) {
+            Some(GraphStep::Enter(pose)) => self.set_visual_state(pose, self.emotion, facing),
+            Some(GraphStep::Transition { clip, reversed }) => self.play_transition(&clip, reversed),
+            None => {
+                // no turning around halfway through lying down
+                if facing != self.facing && !self.state_machine.in_transition() {
+                    self.set_visual_state(self.pose, self.emotion, facing);
+                }
+            }
         }
 
-        if let Some(player) = &mut self.sprite_player {
-            println!("updating animation with dt value {}", dt);
-            player.update(dt);
+        let signals = match &mut self.animator {
+            Some(animator) => animator.update(dt),
+            None => Vec::new(),
+        };
+
+        if signals.contains(&AnimationSignal::TransitionFinished)
+            && let Some(pose) = self.state_machine.finish_transition() {
+            self.set_visual_state(pose, self.emotion, self.facing);
         }
+
+        signals
+    }
+
+    /// The current frame placed with its pivot (the feet) on `position`
+    pub fn sprite(&self) -> Option<Sprite<'_>> {
+        // mud shows as a brown tint, up to half way
+        let mud = self.mud_level.as_f64().clamp(0.0, 1.0) as f32;
+        self.animator.as_ref().map(|animator| Sprite {
+            tint: Color::WHITE.lerp(MUD_COLOR, mud * 0.5),
+            ..Sprite::new(animator.current(), self.position)
+        })
+    }
+
+    /// Whether `point` (in the world) is on the dog's current frame, for clicks
+    pub fn covers(&self, point: Vector2) -> bool {
+        self.sprite().is_some_and(|sprite| sprite.dest_rect().check_collision_point_rec(point))
     }
 
     // draw the dog using the player's current frame
-    pub fn draw(&self, d: &mut RaylibDrawHandle) {
-        if let Some(animation_player) = &self.sprite_player {
-            let src = animation_player.current_frame_rect();
-            // destination rect (example: keep source size, place at position)
-
-            println!("Dimensions. {},{} - {}x{}", self.position.x, self.position.y, src.width, src.height);
-
-            let x = self.position.x;
-            let y = self.position.y;
-            let dest_rect = Rectangle::new(x, y, src.width, src.height);
-            // origin for rotation/scale - top-left here ;adjust to center if desired
-            let origin = Vector2::new(0.0, 0.0);
-
-            d.draw_texture_pro(
-                &animation_player.texture(),
-                src,
-                dest_rect,
-                origin,
-                0.0,
-                raylib::color::Color::WHITE
-            );
+    pub fn draw(&self, d: &mut DrawList) {
+        if let Some(sprite) = self.sprite() {
+            sprite.draw(d);
         }
     }
 }
@@ -259,5 +451,114 @@ impl Dog {
 
 fn calculate_base_drain(drain_rate: &DrainRate, elapsed: f64) -> Percent {
     let ratio = elapsed / drain_rate.duration.as_secs_f64();
-    Percent::new(ratio * drain_rate.percent.as_f64())
+    drain_rate.percent.scaled(ratio)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::animation::{AnimationDescriptor, Sheet};
+    use crate::draw::{self, DrawCommand, DrawList};
+
+    // 64x64 frames with the feet at (30, 64), like the pixeldoggies
+    const PIVOT: Vector2 = Vector2 { x: 30.0, y: 64.0 };
+
+    /// A standing dog with a two frame sheet that was never loaded, so it can be drawn without a window
+    pub(crate) fn test_dog() -> (Dog, Sheet) {
+        let sheet = draw::test_sheet(128, 64);
+        let frames = vec![Rectangle::new(0.0, 0.0, 64.0, 64.0), Rectangle::new(64.0, 0.0, 64.0, 64.0)];
+        let idle = AnimationDescriptor::new(sheet.clone(), frames, 0.1, true).with_pivot(PIVOT);
+
+        let mut bank = AnimationBank::new();
+        bank.insert_clip("idle", idle);
+        bank.bind(AnimationKey { pose: Pose::Standing, emotion: Emotion::Neutral, facing: Facing::Right }, "idle");
+
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let breed = DogBreed::Labrador;
+        let dog = Dog::with_animations("Biscuit".to_string(), breed, Gender::Girl, date, Genome::for_breed(breed), Lineage::founder(), Arc::new(bank));
+        (dog, sheet)
+    }
+
+    fn only_texture(list: &DrawList) -> (Rectangle, Rectangle, Color) {
+        let [(_, DrawCommand::Texture { source, dest, tint, .. })] = list.commands() else {
+            panic!("expected one texture, got {:?}", list.commands());
+        };
+        (*source, *dest, *tint)
+    }
+
+    #[test]
+    fn draws_the_current_frame_with_its_feet_on_the_position() {
+        let (mut dog, sheet) = test_dog();
+        dog.position = Vector2::new(100.0, 50.0);
+
+        let mut list = DrawList::new();
+        dog.draw(&mut list);
+
+        let [(pass, DrawCommand::Texture { texture, .. })] = list.commands() else { panic!("expected one texture") };
+        assert!(Arc::ptr_eq(texture, &sheet));
+        // the dog draws wherever it's told, the camera is up to whoever calls it
+        assert!(!pass.in_world());
+
+        let (source, dest, tint) = only_texture(&list);
+        assert_eq!(source, Rectangle::new(0.0, 0.0, 64.0, 64.0));
+        assert_eq!(dest, Rectangle::new(70.0, -14.0, 64.0, 64.0));
+        assert_eq!((tint.r, tint.g, tint.b, tint.a), (255, 255, 255, 255));
+    }
+
+    #[test]
+    fn facing_left_flips_the_frame_around_the_feet() {
+        let (mut dog, _) = test_dog();
+        dog.position = Vector2::new(100.0, 50.0);
+        dog.set_visual_state(Pose::Standing, Emotion::Neutral, Facing::Left);
+
+        let mut list = DrawList::new();
+        dog.draw(&mut list);
+
+        let (source, dest, _) = only_texture(&list);
+        assert_eq!(source.width, -64.0);
+        assert_eq!(dest.x, 100.0 - (64.0 - PIVOT.x));
+    }
+
+    #[test]
+    fn mud_builds_up_in_the_wet_and_dries_off_in_the_dry() {
+        let (mut dog, _) = test_dog();
+        let an_hour_ago = || Instant::now() - Duration::from_secs(3600);
+
+        dog.last_drain_applied = an_hour_ago();
+        dog.apply_drains(&Surroundings { wetness: 0.5, ..Surroundings::default() });
+        assert!((dog.mud_level.as_f64() - 0.6).abs() < 0.01, "{}", dog.mud_level.as_f64());
+
+        dog.last_drain_applied = an_hour_ago();
+        dog.apply_drains(&Surroundings::default());
+        assert!((dog.mud_level.as_f64() - 0.3).abs() < 0.01, "{}", dog.mud_level.as_f64());
+
+        // and stays clean once it's all gone
+        dog.last_drain_applied = Instant::now() - Duration::from_secs(2 * 3600);
+        dog.apply_drains(&Surroundings::default());
+        assert_eq!(dog.mud_level.as_f64(), 0.0);
+    }
+
+    #[test]
+    fn a_muddy_dog_is_tinted_brown() {
+        let (mut dog, _) = test_dog();
+        dog.mud_level = Percent::new(100.0);
+
+        let mut list = DrawList::new();
+        dog.draw(&mut list);
+
+        let (_, _, tint) = only_texture(&list);
+        assert!(tint.r > tint.g && tint.g > tint.b, "tint {:?} isn't brown", tint);
+        assert!(tint.b < 200);
+    }
+
+    #[test]
+    fn a_dog_without_animations_draws_nothing() {
+        let (mut dog, _) = test_dog();
+        dog.animator = None;
+
+        let mut list = DrawList::new();
+        dog.draw(&mut list);
+
+        assert!(list.commands().is_empty());
+    }
 }
@@ -0,0 +1,246 @@
+use crate::animation::Sheet;
+use raylib::prelude::{BlendMode, Camera2D, RaylibBlendModeExt, RaylibDraw, RaylibMode2DExt, Rectangle, Vector2};
+use raylib::color::Color;
+use std::ops::{Deref, DerefMut};
+
+/// One thing drawn, in whatever space its `Pass` says
+#[derive(Clone, Debug)]
+pub enum DrawCommand {
+    Clear(Color),
+    Texture {
+        texture: Sheet,
+        source: Rectangle,
+        dest: Rectangle,
+        // what `dest` is placed and rotated around
+        origin: Vector2,
+        rotation: f32,
+        tint: Color,
+    },
+    Text { text: String, x: i32, y: i32, size: i32, color: Color },
+    Rectangle { x: i32, y: i32, width: i32, height: i32, color: Color },
+    Circle { center: Vector2, radius: f32, color: Color },
+    Line { from: Vector2, to: Vector2, color: Color },
+    Triangle { a: Vector2, b: Vector2, c: Vector2, color: Color },
+}
+
+/// How a command gets onto the screen: through a camera (world space) or
+/// not (screen space), and how it blends with what's already there
+#[derive(Clone, Copy, Debug)]
+pub struct Pass {
+    pub camera: Option<Camera2D>,
+    pub blend: BlendMode,
+}
+
+impl Pass {
+    const SCREEN: Pass = Pass { camera: None, blend: BlendMode::BLEND_ALPHA };
+
+    pub fn in_world(&self) -> bool {
+        self.camera.is_some()
+    }
+
+    fn same_as(&self, other: &Pass) -> bool {
+        let same_camera = match (self.camera, other.camera) {
+            (None, None) => true,
+            (Some(a), Some(b)) => (a.offset, a.target, a.rotation, a.zoom) == (b.offset, b.target, b.rotation, b.zoom),
+            _ => false,
+        };
+        same_camera && self.blend == other.blend
+    }
+}
+
+/// A frame's worth of drawing, written down instead of drawn. The game
+/// fills one in, tests look at it, and `execute` puts it on the screen.
+/// The methods are named after raylib's so drawing code reads the same.
+pub struct DrawList {
+    commands: Vec<(Pass, DrawCommand)>,
+    pass: Pass,
+}
+
+impl DrawList {
+    pub fn new() -> Self {
+        Self { commands: Vec::new(), pass: Pass::SCREEN }
+    }
+
+    /// Ready for the next frame, keeping the memory
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.pass = Pass::SCREEN;
+    }
+
+    pub fn commands(&self) -> &[(Pass, DrawCommand)] {
+        &self.commands
+    }
+
+    /// Every piece of text, in the order it was drawn
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        self.commands.iter().filter_map(|(_, command)| match command {
+            DrawCommand::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    fn push(&mut self, command: DrawCommand) {
+        self.commands.push((self.pass, command));
+    }
+
+    /// Everything drawn through the scope is in world space, seen by `camera`
+    pub fn begin_world(&mut self, camera: Camera2D) -> DrawScope<'_> {
+        let pass = Pass { camera: Some(camera), ..self.pass };
+        DrawScope::new(self, pass)
+    }
+
+    /// Everything drawn through the scope blends with `blend`
+    pub fn begin_blend(&mut self, blend: BlendMode) -> DrawScope<'_> {
+        let pass = Pass { blend, ..self.pass };
+        DrawScope::new(self, pass)
+    }
+
+    pub fn clear_background(&mut self, color: Color) {
+        self.push(DrawCommand::Clear(color));
+    }
+
+    pub fn draw_texture_pro(&mut self, texture: &Sheet, source: Rectangle, dest: Rectangle, origin: Vector2, rotation: f32, tint: Color) {
+        self.push(DrawCommand::Texture { texture: texture.clone(), source, dest, origin, rotation, tint });
+    }
+
+    pub fn draw_text(&mut self, text: &str, x: i32, y: i32, size: i32, color: Color) {
+        self.push(DrawCommand::Text { text: text.to_string(), x, y, size, color });
+    }
+
+    pub fn draw_rectangle(&mut self, x: i32, y: i32, width: i32, height: i32, color: Color) {
+        self.push(DrawCommand::Rectangle { x, y, width, height, color });
+    }
+
+    pub fn draw_circle_v(&mut self, center: Vector2, radius: f32, color: Color) {
+        self.push(DrawCommand::Circle { center, radius, color });
+    }
+
+    pub fn draw_line_v(&mut self, from: Vector2, to: Vector2, color: Color) {
+        self.push(DrawCommand::Line { from, to, color });
+    }
+
+    pub fn draw_triangle(&mut self, a: Vector2, b: Vector2, c: Vector2, color: Color) {
+        self.push(DrawCommand::Triangle { a, b, c, color });
+    }
+
+    /// Draws the list for real, opening the camera and blend modes around
+    /// each run of commands that share them
+    pub fn execute(&self, d: &mut impl RaylibDraw) {
+        for run in self.commands.chunk_by(|(a, _), (b, _)| a.same_as(b)) {
+            let pass = run[0].0;
+            let commands = run.iter().map(|(_, command)| command);
+
+            match (pass.camera, pass.blend) {
+                (None, BlendMode::BLEND_ALPHA) => execute_all(d, commands),
+                (None, blend) => execute_all(&mut d.begin_blend_mode(blend), commands),
+                (Some(camera), BlendMode::BLEND_ALPHA) => execute_all(&mut d.begin_mode2D(camera), commands),
+                (Some(camera), blend) => {
+                    let mut world = d.begin_mode2D(camera);
+                    execute_all(&mut world.begin_blend_mode(blend), commands);
+                },
+            }
+        }
+    }
+}
+
+fn execute_all<'a>(d: &mut impl RaylibDraw, commands: impl Iterator<Item = &'a DrawCommand>) {
+    for command in commands {
+        match command {
+            DrawCommand::Clear(color) => d.clear_background(*color),
+            DrawCommand::Texture { texture, source, dest, origin, rotation, tint } => {
+                d.draw_texture_pro(&**texture, *source, *dest, *origin, *rotation, *tint);
+            },
+            DrawCommand::Text { text, x, y, size, color } => d.draw_text(text, *x, *y, *size, *color),
+            DrawCommand::Rectangle { x, y, width, height, color } => d.draw_rectangle(*x, *y, *width, *height, *color),
+            DrawCommand::Circle { center, radius, color } => d.draw_circle_v(*center, *radius, *color),
+            DrawCommand::Line { from, to, color } => d.draw_line_v(*from, *to, *color),
+            DrawCommand::Triangle { a, b, c, color } => d.draw_triangle(*a, *b, *c, *color),
+        }
+    }
+}
+
+/// A camera or blend mode in use, like raylib's `begin_mode2D`. Drawing
+/// goes back to how it was when this is dropped.
+pub struct DrawScope<'a> {
+    list: &'a mut DrawList,
+    outer: Pass,
+}
+
+impl<'a> DrawScope<'a> {
+    fn new(list: &'a mut DrawList, pass: Pass) -> Self {
+        let outer = list.pass;
+        list.pass = pass;
+        Self { list, outer }
+    }
+}
+
+impl Deref for DrawScope<'_> {
+    type Target = DrawList;
+    fn deref(&self) -> &DrawList { self.list }
+}
+
+impl DerefMut for DrawScope<'_> {
+    fn deref_mut(&mut self) -> &mut DrawList { self.list }
+}
+
+impl Drop for DrawScope<'_> {
+    fn drop(&mut self) {
+        self.list.pass = self.outer;
+    }
+}
+
+/// A texture that was never loaded, so tests can make sheets without a
+/// window. It has the size you ask for and nothing in it.
+#[cfg(test)]
+pub fn test_sheet(width: i32, height: i32) -> Sheet {
+    use raylib::consts::PixelFormat;
+    use raylib::ffi;
+
+    let raw = ffi::Texture2D { id: 0, width, height, mipmaps: 1, format: PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8 as i32 };
+    // SAFETY: raylib only unloads textures with an id, so dropping this never touches the GPU
+    std::sync::Arc::new(unsafe { raylib::prelude::Texture2D::from_raw(raw) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn camera(x: f32) -> Camera2D {
+        Camera2D { offset: Vector2::new(0.0, 0.0), target: Vector2::new(x, 0.0), rotation: 0.0, zoom: 1.0 }
+    }
+
+    #[test]
+    fn records_commands_in_order_with_their_pass() {
+        let sheet = test_sheet(16, 16);
+        let mut list = DrawList::new();
+
+        list.clear_background(Color::BLACK);
+        {
+            let mut world = list.begin_world(camera(10.0));
+            world.draw_texture_pro(&sheet, Rectangle::new(0.0, 0.0, 16.0, 16.0), Rectangle::new(4.0, 8.0, 16.0, 16.0), Vector2::new(0.0, 0.0), 0.0, Color::WHITE);
+            let mut glow = world.begin_blend(BlendMode::BLEND_ADDITIVE);
+            glow.draw_circle_v(Vector2::new(1.0, 2.0), 3.0, Color::YELLOW);
+        }
+        list.draw_text("hello", 12, 100, 12, Color::GREEN);
+
+        let commands = list.commands();
+        assert_eq!(commands.len(), 4);
+
+        assert!(matches!(commands[0], (pass, DrawCommand::Clear(_)) if !pass.in_world()));
+
+        let (pass, DrawCommand::Texture { texture, dest, .. }) = &commands[1] else { panic!("expected a texture, got {:?}", commands[1]) };
+        assert!(Arc::ptr_eq(texture, &sheet));
+        assert_eq!(*dest, Rectangle::new(4.0, 8.0, 16.0, 16.0));
+        assert_eq!(pass.camera.map(|camera| camera.target.x), Some(10.0));
+        assert_eq!(pass.blend, BlendMode::BLEND_ALPHA);
+
+        let (pass, DrawCommand::Circle { .. }) = &commands[2] else { panic!("expected a circle, got {:?}", commands[2]) };
+        assert!(pass.in_world());
+        assert_eq!(pass.blend, BlendMode::BLEND_ADDITIVE);
+
+        // back in screen space once the scopes are gone
+        assert!(matches!(&commands[3], (pass, DrawCommand::Text { x: 12, y: 100, .. }) if !pass.in_world() && pass.blend == BlendMode::BLEND_ALPHA));
+        assert_eq!(list.texts().collect::<Vec<_>>(), ["hello"]);
+    }
+}
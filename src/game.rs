@@ -1,19 +1,78 @@
-use raylib::prelude::{RaylibDraw, RaylibDrawHandle, RaylibHandle, RaylibThread, KeyboardKey, Texture2D, Rectangle, Vector2};
+use raylib::prelude::{RaylibHandle, RaylibThread, KeyboardKey, Vector2};
 use raylib::color::Color;
 use crate::TICKS_PER_SEC;
-use crate::dog::Dog;
+use crate::animation::Pose;
+use crate::animator::OneShotPriority;
+use crate::assets::Assets;
+use crate::breeding::{whelp_litter, Pedigree, LITTER_GAP_DAYS, MIN_BREEDING_AGE_DAYS};
+use crate::camera::GameCamera;
+use crate::collision::Solidity;
+use crate::daylight::{DayPhase, Daylight};
+use crate::dog::{Dog, DogId, Surroundings};
+use crate::draw::DrawList;
+use crate::input::Input;
+use crate::particles;
 use crate::player::Player;
-use crate::types::{Food,Percent};
+use crate::render::{self, SortRules, Sprite};
+use crate::rng::Rng;
+use crate::screen;
+use crate::scene::{Ambient, Scene, SceneDef, SceneTransition, TransitionStep};
+use crate::shelter::{self, Shelter, ShelterCandidate};
+use crate::types::{Food, Gender, Percent};
+use crate::weather::{Weather, WeatherOverlay};
 use std::time::{Instant, Duration};
+use chrono::Timelike;
 
 const START_DATE: chrono::NaiveDate = chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
 const GAME_TIME_PASSING_SPEED: f64 = 10.0; // one day in game time per 10 minutes of real world time
+// the clock starts this far into START_DATE, so the first day begins in the morning
+const START_HOUR: f64 = 8.0;
+const SHELTER_SIZE: usize = 6;
+// the scene where the dog that was there before yours lives
+const COMPANION_HOME: &str = "house";
+// how close, in map pixels, two dogs have to be to have puppies
+const MATING_DISTANCE: f32 = 32.0;
+// how long a notice stays up
+const NOTICE_TICKS: i32 = 4 * TICKS_PER_SEC;
+// how far down the screen the forest floor is, 0 = top 1 = bottom
+const GROUND_LINE: f32 = 0.85;
+// the first one is where the game starts
+const SCENES: [SceneDef; 2] = [
+    SceneDef {
+        name: "yard",
+        map: "images/home/Exterior.tmx",
+        ambient: Ambient { forest: Some(8), clear_color: Color::BLACK, outdoors: true },
+        particle_cap: 300,
+    },
+    SceneDef {
+        name: "house",
+        map: "images/home/Interior1.tmx",
+        ambient: Ambient { forest: None, clear_color: Color::BLACK, outdoors: false },
+        particle_cap: 150,
+    },
+];
+// map layers the dog can't walk through. A layer's "solid" property wins over this list
+const SOLID_LAYERS: [(&str, Solidity); 5] = [
+    ("Walls", Solidity::Full),
+    ("Fence", Solidity::Full),
+    ("House_wall", Solidity::Full),
+    // furniture, trees and rocks: blocked at the foot, walk behind the rest
+    ("Boxes", Solidity::Base),
+    ("Objects*", Solidity::Base),
+];
+// map pixels a second
+const DOG_WALK_SPEED: f32 = 48.0;
+// map layers the dog can walk behind. A layer's "ysort" property wins over this list
+const SORTED_LAYERS: [&str; 7] = ["Objects*", "Fence", "House_*", "windows*", "Boxes", "Walls", "Windows"];
+// map layers that light up at night. A layer's "glow" property wins over this list
+const GLOWING_LAYERS: [&str; 2] = ["windows*", "Windows"];
 
 pub enum GameState {
     Initializing,
     Splash(SplashData),
     CollectingInfo,
     MainMenu,
+    Shelter(Shelter),
     Playing,
     Paused,
     Quit,
@@ -31,29 +90,67 @@ impl SplashData {
 
 pub struct Game {
     state: GameState,
-    background: Texture2D,
+    pub assets: Assets,
     // thread: RaylibThread,
     pub ticks: i32,
     pub dog: Option<Dog>,
     pub player: Option<Player>,
+    pub pedigree: Pedigree,
+    scenes: Vec<Scene>,
+    current_scene: usize,
+    // walking through a door
+    transition: Option<SceneTransition>,
+    sort_rules: SortRules,
+    camera: GameCamera,
+    // every day's weather comes from this, so the forecast holds
+    weather_seed: u64,
+    weather: WeatherOverlay,
+    // picked at the shelter, waiting for main.rs to load its sprites
+    adopted: Option<ShelterCandidate>,
+    // something that just happened and the tick it happened on
+    notice: Option<(String, i32)>,
     screen_w: f32,
+    // always screen::VIRTUAL_H, only the width follows the window. the HUD
+    // and shelter text sit at fixed rows laid out for that height
     screen_h: f32,
 }
 
 impl Game {
-    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> Self {
-        let background = rl.load_texture(thread, "images/forest_trees/8/2304x1296.png")
-            .expect("failed to load background texture");
+    /// `screen_w` and `screen_h` are the virtual screen's size, see `screen::Screen`
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, screen_w: i32, screen_h: i32) -> anyhow::Result<Self> {
+        let mut assets = Assets::new(rl, thread)?;
 
-        let screen_w = rl.get_screen_width() as f32;
-        let screen_h = rl.get_screen_height() as f32;
+        let scenes = SCENES.iter()
+            .map(|def| Scene::load(def, &mut assets, rl, thread, &SOLID_LAYERS))
+            .collect();
+
+        Ok(Self::with_scenes(assets, scenes, screen_w, screen_h))
+    }
+
+    /// A game in `scenes`, which are already loaded. The first one is where it starts.
+    pub fn with_scenes(assets: Assets, scenes: Vec<Scene>, screen_w: i32, screen_h: i32) -> Self {
+        debug_assert_eq!(screen_h, screen::VIRTUAL_H, "the HUD is laid out for a fixed height");
+        let (screen_w, screen_h) = (screen_w as f32, screen_h as f32);
+        let mut rng = Rng::from_time();
+        let weather_seed = rng.next_u64();
+        let weather = WeatherOverlay::new(Weather::for_day(weather_seed, START_DATE), rng.next_u64());
 
         Self {
             ticks: 0,
-            background: background,
+            assets: assets,
             state: GameState::Initializing,
             dog: None,
             player: None,
+            pedigree: Pedigree::new(),
+            scenes,
+            current_scene: 0,
+            transition: None,
+            sort_rules: SortRules::new(&SORTED_LAYERS),
+            camera: GameCamera::new(screen_w, screen_h),
+            weather_seed,
+            weather,
+            adopted: None,
+            notice: None,
             screen_w: screen_w,
             screen_h: screen_h,
         }
@@ -76,7 +173,133 @@ impl Game {
     }
 
     pub fn set_dog(&mut self, dog: Dog) {
+        self.pedigree.record(&dog);
+
+        // the first dog you bring home finds another one already living there
+        if self.scenes.iter().all(|scene| scene.residents.is_empty()) {
+            let companion = companion_for(&dog, self.date_in_game(), &mut Rng::from_time());
+            self.pedigree.record(&companion);
+            match self.scenes.iter_mut().find(|scene| scene.name == COMPANION_HOME) {
+                Some(home) => home.move_in(companion, "spawn"),
+                None => eprintln!("no '{}' scene for {} to live in", COMPANION_HOME, companion.name),
+            }
+        }
+
         self.dog = Some(dog);
+        self.place_dog("spawn");
+
+        // the old dog's sprites may not be needed anymore
+        self.assets.release_unused();
+    }
+
+    /// The virtual screen changed shape with the window
+    pub fn resize(&mut self, screen_w: i32, screen_h: i32) {
+        debug_assert_eq!(screen_h, screen::VIRTUAL_H, "the HUD is laid out for a fixed height");
+        self.screen_w = screen_w as f32;
+        self.screen_h = screen_h as f32;
+        self.camera.set_screen_size(self.screen_w, self.screen_h);
+    }
+
+    fn scene(&self) -> &Scene {
+        &self.scenes[self.current_scene]
+    }
+
+    /// Puts the dog at the named spawn object of the current scene (the
+    /// middle of the map if there's no such object), nudged out of any
+    /// walls. The camera jumps there too.
+    fn place_dog(&mut self, spawn: &str) {
+        let Some(dog) = &mut self.dog else { return };
+        let scene = &self.scenes[self.current_scene];
+
+        // no map, stand on the ground in the middle of the screen
+        dog.position = scene.arrival(spawn, Dog::body_at)
+            .unwrap_or_else(|| Vector2::new(self.screen_w * 0.5, self.screen_h * GROUND_LINE));
+        self.camera.set_bounds(scene.bounds());
+        self.camera.snap_to(dog.position);
+        // new place, new smells
+        dog.play_clip("sniff", OneShotPriority::Low);
+    }
+
+    /// Starts the fade through a door. The dog keeps everything about it
+    /// except where it stands, see `step_transition`.
+    fn go_through(&mut self, to: &str, spawn: String) {
+        match self.scenes.iter().position(|scene| scene.name == to) {
+            Some(index) => self.transition = Some(SceneTransition::new(index, spawn)),
+            None => eprintln!("a door leads to '{}' but there's no such scene", to),
+        }
+    }
+
+    /// The screen goes dark, the scene swaps, it comes back. The dog and the
+    /// player belong to the game rather than a scene, so they come along as
+    /// they are; only the dog is put down somewhere new. Residents stay put.
+    fn step_transition(&mut self, dt: f32) {
+        let Some(transition) = &mut self.transition else { return };
+
+        match transition.update(dt) {
+            TransitionStep::Fading => {},
+            TransitionStep::Switch => {
+                let spawn = transition.spawn.clone();
+                self.current_scene = transition.to;
+                self.place_dog(&spawn);
+            },
+            TransitionStep::Done => self.transition = None,
+        }
+    }
+
+    /// Your dog and the nearest dog living here have puppies, if they can.
+    /// The litter moves in where they are. Returns what happened, to tell the player.
+    fn breed_with_nearest(&mut self) -> Option<String> {
+        let dog = self.dog.as_ref()?;
+        let today = self.date_in_game();
+        let scene = &mut self.scenes[self.current_scene];
+
+        let distance = |other: &Dog| dog.position.distance_to(other.position);
+        let partner = scene.residents.iter()
+            .filter(|resident| distance(resident) <= MATING_DISTANCE)
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)));
+        let Some(partner) = partner else {
+            return Some(format!("{} has nobody close by to have puppies with", dog.name));
+        };
+
+        let (sire, dam) = if dog.gender == Gender::Boy { (dog, partner) } else { (partner, dog) };
+        if let Some(last) = self.pedigree.last_litter(dam.id)
+            && (today - last).num_days() < LITTER_GAP_DAYS {
+            return Some(format!("{} had puppies not long ago", dam.name));
+        }
+
+        let mut rng = Rng::from_time();
+        let litter = match whelp_litter(sire, dam, today, &mut rng) {
+            Ok(litter) => litter,
+            Err(err) => return Some(err.to_string()),
+        };
+
+        let message = format!("{} and {} had {} puppies!", dam.name, sire.name, litter.len());
+        let (position, animations) = (dam.position, dam.animations.clone());
+        for puppy in litter {
+            let name = rng.pick(&shelter::NAMES).to_string();
+            let mut pup = Dog::from_puppy(name, puppy, today, animations.clone());
+            // tumbling about at their mum's feet
+            pup.position = Vector2::new(position.x + rng.range_f32(-12.0, 12.0), position.y + rng.range_f32(2.0, 8.0));
+            self.pedigree.record(&pup);
+            scene.residents.push(pup);
+        }
+        Some(message)
+    }
+
+    fn notice(&mut self, text: String) {
+        self.notice = Some((text, self.ticks));
+    }
+
+    pub fn show_shelter(&mut self) {
+        let seed = Rng::from_time().next_u64();
+        let shelter = Shelter::generate(seed, SHELTER_SIZE, self.date_in_game());
+        self.state = GameState::Shelter(shelter);
+    }
+
+    /// The dog the player just adopted, if any. main.rs turns it into a `Dog`
+    /// (that needs the raylib handle for textures) and hands it back via `set_dog`.
+    pub fn take_adoption(&mut self) -> Option<ShelterCandidate> {
+        self.adopted.take()
     }
 
     pub fn set_player(&mut self, player: Player) {
@@ -84,55 +307,166 @@ impl Game {
     }
 
     pub fn date_in_game(&self) -> chrono::NaiveDate {
-        // In the game universe, time moves at the rate of 1 day per 3 minutes
-        let minutes_elapsed = f64::from(self.ticks / 3600);
-        let days_elapsed  = (minutes_elapsed / GAME_TIME_PASSING_SPEED) as i64;
+        self.time_in_game().date()
+    }
 
-        let delta = chrono::TimeDelta::days(days_elapsed);
+    pub fn time_in_game(&self) -> chrono::NaiveDateTime {
+        // In the game universe, one day passes every GAME_TIME_PASSING_SPEED minutes
+        let minutes_elapsed = f64::from(self.ticks) / f64::from(TICKS_PER_SEC) / 60.0;
+        let days_elapsed = minutes_elapsed / GAME_TIME_PASSING_SPEED;
+        let seconds = (START_HOUR + days_elapsed * 24.0) * 3600.0;
 
-        START_DATE + delta
+        START_DATE.and_hms_opt(0, 0, 0).unwrap() + chrono::TimeDelta::milliseconds((seconds * 1000.0) as i64)
     }
 
-    fn update_main_menu(&mut self, rl: &RaylibHandle) {
-        if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
-            // TODO: actually, we should be making sure the player and dog are initialized before starting the game
+    /// Hours since midnight on the game clock, 0 to 24
+    pub fn hour_in_game(&self) -> f32 {
+        let time = self.time_in_game().time();
+        time.num_seconds_from_midnight() as f32 / 3600.0
+    }
+
+    /// Today's weather and tomorrow's
+    pub fn forecast(&self) -> (Weather, Weather) {
+        let today = self.date_in_game();
+        let tomorrow = today.succ_opt().unwrap_or(today);
+        (Weather::for_day(self.weather_seed, today), Weather::for_day(self.weather_seed, tomorrow))
+    }
+
+    fn daylight(&self) -> Daylight {
+        Daylight::at(self.hour_in_game(), self.scene().ambient.outdoors)
+    }
+
+    fn update_main_menu(&mut self, input: &Input) {
+        if input.pressed(KeyboardKey::KEY_ENTER) {
+            // TODO: actually, we should be making sure the player is initialized before starting the game
+            if self.dog.is_none() && self.adopted.is_none() {
+                self.show_shelter();
+            } else {
+                self.set_state(GameState::Playing);
+            }
+        }
+    }
+
+    fn update_shelter(&mut self, input: &Input) {
+        let GameState::Shelter(shelter) = &mut self.state else { return };
+
+        if input.pressed(KeyboardKey::KEY_DOWN) {
+            shelter.select_next();
+        } else if input.pressed(KeyboardKey::KEY_UP) {
+            shelter.select_prev();
+        } else if input.pressed(KeyboardKey::KEY_ENTER)
+            && let Some(candidate) = shelter.adopt() {
+            self.adopted = Some(candidate);
             self.set_state(GameState::Playing);
         }
     }
 
-    fn update_playing(&mut self, rl: &RaylibHandle) {
-        if rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+    fn update_playing(&mut self, input: &Input) {
+        if input.pressed(KeyboardKey::KEY_SPACE) {
             // pause on spacebar
             self.set_state(GameState::Paused);
-        } else if rl.is_key_pressed(KeyboardKey::KEY_F) {
-            match &mut self.dog {
-                Some(dog) =>  {
+            return;
+        }
+
+        if input.pressed(KeyboardKey::KEY_EQUAL) {
+            self.camera.zoom_in();
+        } else if input.pressed(KeyboardKey::KEY_MINUS) {
+            self.camera.zoom_out();
+        }
+
+        let dt = 1.0 / TICKS_PER_SEC as f32;
+
+        self.step_transition(dt);
+        let fading = self.transition.is_some();
+
+        // doors, smoke, trees and the cat run on game time, so they stop while paused
+        let (today, _) = self.forecast();
+        self.weather.set_weather(today);
+        let thunder = self.weather.update(dt, self.screen_w, self.screen_h);
+
+        let outdoors = self.scene().ambient.outdoors;
+        let surroundings = Surroundings {
+            darkness: self.daylight().darkness,
+            thirst: today.thirst(outdoors),
+            wetness: today.wetness(outdoors),
+        };
+        let visitors: Vec<(DogId, Vector2)> = self.dog.iter().map(|dog| (dog.id, dog.position)).collect();
+        self.scenes[self.current_scene].update(dt, &surroundings, &visitors);
+        if thunder {
+            for resident in &mut self.scenes[self.current_scene].residents {
+                resident.startle();
+            }
+        }
+        if !fading && input.pressed(KeyboardKey::KEY_M)
+            && let Some(message) = self.breed_with_nearest() {
+            self.notice(message);
+        }
+        let clicked = input.click().map(|at| self.camera.screen_to_world(at));
+        let scene = &mut self.scenes[self.current_scene];
+
+        match &mut self.dog {
+            Some(dog) => {
+                // walk while the arrows are held, but not while going through a door
+                let mut direction = Vector2::new(0.0, 0.0);
+                if !fading {
+                    if input.down(KeyboardKey::KEY_LEFT) { direction.x -= 1.0; }
+                    if input.down(KeyboardKey::KEY_RIGHT) { direction.x += 1.0; }
+                    if input.down(KeyboardKey::KEY_UP) { direction.y -= 1.0; }
+                    if input.down(KeyboardKey::KEY_DOWN) { direction.y += 1.0; }
+                }
+                let length = (direction.x * direction.x + direction.y * direction.y).sqrt().max(1.0);
+                dog.velocity = Vector2::new(direction.x / length * DOG_WALK_SPEED, direction.y / length * DOG_WALK_SPEED);
+
+                if input.pressed(KeyboardKey::KEY_F) {
                     let food = Food::new("kibble".to_string(), Percent::new(15.0));
                     dog.feed(&food);
+                } else if input.pressed(KeyboardKey::KEY_B) {
+                    dog.play_clip("bark", OneShotPriority::Normal);
+                } else if input.pressed(KeyboardKey::KEY_S) {
+                    let pose = if dog.requested_pose == Pose::Sitting { Pose::Standing } else { Pose::Sitting };
+                    dog.request_pose(pose);
+                } else if input.pressed(KeyboardKey::KEY_L) {
+                    let pose = if dog.requested_pose == Pose::Sleeping { Pose::Standing } else { Pose::Sleeping };
+                    dog.request_pose(pose);
+                } else if input.pressed(KeyboardKey::KEY_P) || clicked.is_some_and(|at| dog.covers(at)) {
+                    dog.pet();
+                    // over its back
+                    scene.particles.burst(&particles::HEARTS, Vector2::new(dog.position.x, dog.position.y - 28.0));
+                }
 
-                    dog.update(1.0 / TICKS_PER_SEC as f32);
-                },
-                None => {}
-            }
-        } else {
-            match &mut self.dog {
-                Some(dog) => dog.apply_drains(),
-                None => {}
-            }
+                dog.apply_drains(&surroundings);
+                if thunder {
+                    dog.startle();
+                }
+
+                let signals = dog.update(dt, scene.collision());
+                scene.particles.animation_events(&signals, dog.position, surroundings.wetness);
+                scene.particles.follow_pet(dog);
+
+                // the player has no avatar in the world (see `Player`), so the camera stays on the dog
+                self.camera.follow(dog.position, dt);
+
+                let door = if fading { None } else { scene.door_at(dog.body()) };
+                if let Some(door) = door {
+                    let (to, spawn) = (door.to.clone(), door.spawn.clone());
+                    self.go_through(&to, spawn);
+                }
+            },
+            None => {}
         }
     }
 
-    fn update_paused(&mut self, rl: &RaylibHandle) {
-        if rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+    fn update_paused(&mut self, input: &Input) {
+        if input.pressed(KeyboardKey::KEY_SPACE) {
             // unpause on spacebar
             self.set_state(GameState::Playing);
         }
     }
 
-    pub fn update(&mut self, rl: &RaylibHandle) {
+    pub fn update(&mut self, input: &Input) {
         self.ticks += 1;
 
-        if rl.is_key_pressed(KeyboardKey::KEY_Q) {
+        if input.pressed(KeyboardKey::KEY_Q) {
            self.set_state(GameState::Quit)
         }
 
@@ -147,13 +481,16 @@ impl Game {
             GameState::CollectingInfo => {
             },
             GameState::MainMenu => {
-                self.update_main_menu(&rl);
+                self.update_main_menu(input);
+            },
+            GameState::Shelter(_) => {
+                self.update_shelter(input);
             },
             GameState::Playing => {
-                self.update_playing(&rl);
+                self.update_playing(input);
             },
             GameState::Paused => {
-                self.update_paused(&rl);
+                self.update_paused(input);
             },
             GameState::Quit => {
                 // do nothing. main.rs will take it from here.
@@ -161,41 +498,72 @@ impl Game {
         }
     }
 
-    fn draw_background(&self, d: &mut RaylibDrawHandle) {
-        // texture original size
-        let tex_w = self.background.width as f32;
-        let tex_h = self.background.height as f32;
+    fn draw_background(&self, d: &mut DrawList) {
+        if let Some(background) = self.scene().background() {
+            background.draw(d, &self.camera.camera2d(), self.screen_w, self.screen_h);
+        }
+    }
+
+    /// Draws the current map with the sprites sorted in among its layers.
+    /// Call inside the camera's 2D mode, everything here is in map pixels.
+    fn draw_map(&self, d: &mut DrawList, sprites: &mut [Sprite]) {
+        match &self.scene().map {
+            Some(map) => render::draw_layered(d, map, Vector2::new(0.0, 0.0), 1.0, &self.sort_rules, sprites),
+            None => render::draw_sorted(d, sprites),
+        }
+    }
+
+    fn draw_shelter(&self, d: &mut DrawList, shelter: &Shelter) {
+        let today = self.date_in_game();
 
-        // "cover" scaling while preserving aspect ratio (fills screen, may crop)
-        let scale = f32::max(self.screen_w / tex_w, self.screen_h / tex_h);
-        let dest_w = tex_w * scale;
-        let dest_h = tex_h * scale;
-        let dest_x = (self.screen_w - dest_w) * 0.5;
-        let dest_y = (self.screen_h - dest_h) * 0.5;
+        d.draw_text("Adopt a dog", 12, 130, 20, Color::PINK);
+
+        for (i, candidate) in shelter.candidates.iter().enumerate() {
+            let color = if i == shelter.selected { Color::YELLOW } else { Color::LIGHTGRAY };
+            let line = format!("{} - {:?}", candidate.name, candidate.breed);
+            d.draw_text(&line, 24, 160 + 20 * i as i32, 16, color);
+        }
 
-        let src_rect = Rectangle::new(0.0, 0.0, tex_w, tex_h);
-        let dest_rect = Rectangle::new(dest_x, dest_y, dest_w, dest_h);
+        if let Some(candidate) = shelter.current() {
+            // details to the right of the list, wrapped to whatever room is left
+            let x = (self.screen_w * 0.4) as i32;
+            let columns = ((self.screen_w as i32 - x - 12) / 6).max(20) as usize;
+            let p = &candidate.genome.personality;
+
+            d.draw_text(&candidate.name, x, 160, 20, Color::YELLOW);
+            d.draw_text(&format!("{:?} {:?}, {:.1} years old", candidate.breed, candidate.gender, candidate.age_years(today)), x, 186, 12, Color::WHITE);
+            d.draw_text(&format!("energy {}  social {}  smarts {}  bravery {}", p.energy, p.sociability, p.trainability, p.boldness), x, 204, 12, Color::WHITE);
+
+            // crude word wrap so the backstory stays on screen
+            let mut line = String::new();
+            let mut y = 230;
+            for word in candidate.backstory.split_whitespace() {
+                if line.len() + word.len() > columns {
+                    d.draw_text(&line, x, y, 12, Color::LIGHTGRAY);
+                    line.clear();
+                    y += 16;
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+            }
+            d.draw_text(&line, x, y, 12, Color::LIGHTGRAY);
+        }
 
-        d.draw_texture_pro(
-            &self.background,
-            src_rect,
-            dest_rect,
-            Vector2::new(0.0, 0.0),
-            0.0,
-            Color::WHITE,
-        );
+        d.draw_text("up/down to browse, enter to adopt", 12, self.screen_h as i32 - 24, 12, Color::GREEN);
     }
 
-    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+    pub fn draw(&self, d: &mut DrawList) {
         
-        d.clear_background(Color::BLACK);
+        d.clear_background(self.scene().ambient.clear_color);
         self.draw_background(d);
 
         d.draw_text("All my doggies", 12, 12, 20, Color::PINK);
 
         let status: &str;
 
-        match self.state {
+        match &self.state {
             GameState::Initializing => {
                 status = "initalizing...";
             },
@@ -208,14 +576,57 @@ impl Game {
             GameState::MainMenu => {
                 status = "main menu";
             },
+            GameState::Shelter(shelter) => {
+                status = "shelter";
+                self.draw_shelter(d, shelter);
+            },
             GameState::Playing => {
                 status = "playing";
+
+                let mut sprites: Vec<Sprite> = self.dog.iter().filter_map(|dog| dog.sprite())
+                    .chain(self.scene().resident_sprites())
+                    .collect();
+                {
+                    let mut world = d.begin_world(self.camera.camera2d());
+                    self.draw_map(&mut world, &mut sprites);
+                    self.scene().particles.draw(&mut world);
+                }
+
+                if self.scene().ambient.outdoors {
+                    self.weather.draw(d, self.screen_w, self.screen_h);
+                }
+
+                // time of day over the world, then the windows lit up on top
+                let daylight = self.daylight();
+                daylight.draw(d, self.screen_w, self.screen_h);
+                if let Some(map) = &self.scene().map {
+                    let mut world = d.begin_world(self.camera.camera2d());
+                    daylight.draw_glow(&mut world, map, &GLOWING_LAYERS);
+                }
+                self.weather.draw_flash(d, self.screen_w, self.screen_h, self.scene().ambient.outdoors);
+
+                if let Some(transition) = &self.transition {
+                    d.draw_rectangle(0, 0, self.screen_w as i32, self.screen_h as i32, Color::BLACK.fade(transition.darkness()));
+                }
+
+                let clock = format!("{} ({:?})", self.time_in_game().format("%a %-d %b, %H:%M"), DayPhase::at(self.hour_in_game()));
+                d.draw_text(&clock, 12, 118, 12, Color::WHITE);
+                let (today, tomorrow) = self.forecast();
+                d.draw_text(&format!("{}, tomorrow {}", today.name(), tomorrow.name()), 12, 134, 12, Color::SKYBLUE);
+
+                if let Some((text, at)) = &self.notice
+                    && self.ticks - at < NOTICE_TICKS {
+                    d.draw_text(text, 12, self.screen_h as i32 - 24, 12, Color::PINK);
+                }
+
                 match &self.dog {
                     Some(dog) => {
                         let text = format!("food level {}", dog.food_level.as_f64());
                         d.draw_text(&text, 12, 150, 12, Color::YELLOW);
-
-                        dog.draw(d);
+                        let text = format!("energy level {:.0}%", dog.energy_level.as_f64() * 100.0);
+                        d.draw_text(&text, 12, 166, 12, Color::YELLOW);
+                        let text = format!("mud level {:.0}%  anxiety level {:.0}%", dog.mud_level.as_f64() * 100.0, dog.anxiety_level.as_f64() * 100.0);
+                        d.draw_text(&text, 12, 182, 12, Color::YELLOW);
                     },
                     None => {}
                 }
@@ -223,6 +634,16 @@ impl Game {
             },
             GameState::Paused => {
                 status = "paused";
+
+                // family tree of the current dog while the game is paused
+                if let Some(dog) = &self.dog {
+                    let coat = dog.coat();
+                    let looks = format!("{:?} {:?} {:?} coat, {:?}", coat.color, coat.pattern, coat.length, dog.size());
+                    d.draw_text(&looks, 12, 134, 12, Color::YELLOW);
+                    for (i, line) in self.pedigree.describe(dog.id, 3).iter().enumerate() {
+                        d.draw_text(line, 12, 150 + 16 * i as i32, 12, Color::YELLOW);
+                    }
+                }
             },
             GameState::Quit => {
                 status = "quitting";
@@ -231,4 +652,212 @@ impl Game {
 
         d.draw_text(status, 12, 100, 12, Color::GREEN);
     }
-}
\ No newline at end of file
+}
+
+/// A grown dog of the other sex to `dog`, so the two can have puppies one
+/// day. Drawn with the same sprites as `dog`.
+fn companion_for(dog: &Dog, today: chrono::NaiveDate, rng: &mut Rng) -> Dog {
+    let mut companion = ShelterCandidate::generate(rng, today);
+    while companion.name == dog.name {
+        companion = ShelterCandidate::generate(rng, today);
+    }
+
+    companion.gender = match dog.gender {
+        Gender::Boy => Gender::Girl,
+        Gender::Girl => Gender::Boy,
+    };
+    let grown = today - chrono::TimeDelta::days(MIN_BREEDING_AGE_DAYS);
+    companion.date_of_birth = companion.date_of_birth.min(grown);
+
+    companion.with_animations(dog.animations.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dog::tests::test_dog;
+    use crate::draw::{self, DrawCommand};
+    use std::sync::Arc;
+
+    // every scene without its map, so nothing needs a window
+    fn test_game() -> Game {
+        let assets = Assets::with_placeholder(draw::test_sheet(64, 64));
+        let scenes = SCENES.iter().map(Scene::empty).collect();
+        Game::with_scenes(assets, scenes, 640, 360)
+    }
+
+    fn position_of_text(list: &DrawList, wanted: impl Fn(&str) -> bool) -> Option<usize> {
+        list.commands().iter().position(|(_, command)| matches!(command, DrawCommand::Text { text, .. } if wanted(text)))
+    }
+
+    #[test]
+    fn main_menu_shows_the_title_and_status() {
+        let mut game = test_game();
+        game.show_main_menu();
+
+        let mut list = DrawList::new();
+        game.draw(&mut list);
+
+        assert!(matches!(list.commands().first(), Some((_, DrawCommand::Clear(_)))));
+        let texts: Vec<&str> = list.texts().collect();
+        assert!(texts.contains(&"All my doggies"));
+        assert!(texts.contains(&"main menu"));
+    }
+
+    #[test]
+    fn shelter_lists_the_dogs_up_for_adoption() {
+        let mut game = test_game();
+        game.show_shelter();
+
+        let mut list = DrawList::new();
+        game.draw(&mut list);
+
+        let GameState::Shelter(shelter) = &game.state else { panic!("not at the shelter") };
+        for candidate in &shelter.candidates {
+            assert!(list.texts().any(|text| text.starts_with(&candidate.name)), "{} isn't listed", candidate.name);
+        }
+        assert!(list.texts().any(|text| text == "Adopt a dog"));
+    }
+
+    #[test]
+    fn playing_draws_the_dog_in_the_world_under_the_hud() {
+        let mut game = test_game();
+        let (dog, sheet) = test_dog();
+        game.set_dog(dog);
+        game.set_state(GameState::Playing);
+
+        let mut list = DrawList::new();
+        game.draw(&mut list);
+
+        let dog_at = list.commands().iter().position(|(_, command)| {
+            matches!(command, DrawCommand::Texture { texture, .. } if Arc::ptr_eq(texture, &sheet))
+        }).expect("the dog isn't drawn");
+        let (pass, _) = &list.commands()[dog_at];
+        assert!(pass.in_world());
+
+        let hud_at = position_of_text(&list, |text| text.starts_with("food level")).expect("no food level");
+        assert!(!list.commands()[hud_at].0.in_world());
+        assert!(dog_at < hud_at, "the HUD is drawn under the world");
+        assert!(position_of_text(&list, |text| text == "playing").is_some());
+    }
+
+    #[test]
+    fn clicking_on_the_dog_pets_it() {
+        let mut game = test_game();
+        game.set_dog(test_dog().0);
+        game.set_state(GameState::Playing);
+        let social = |game: &Game| game.dog.as_ref().unwrap().social_battery.as_f64();
+        let before = social(&game);
+
+        // off to the side does nothing
+        game.update(&Input::with_click(Vector2::new(20.0, 20.0)));
+        assert_eq!(social(&game), before);
+
+        // its back, a little above the feet
+        let dog = game.dog.as_ref().unwrap().position;
+        let on_the_dog = game.camera.world_to_screen(Vector2::new(dog.x, dog.y - 20.0));
+        game.update(&Input::with_click(on_the_dog));
+        assert!(social(&game) > before);
+    }
+
+    #[test]
+    fn your_first_dog_finds_a_companion_at_home() {
+        let mut game = test_game();
+        let (dog, _) = test_dog();
+        let (gender, id) = (dog.gender, dog.id);
+        game.set_dog(dog);
+
+        let home = game.scenes.iter().find(|scene| scene.name == COMPANION_HOME).unwrap();
+        let [companion] = &home.residents[..] else { panic!("expected one resident, got {}", home.residents.len()) };
+        assert_ne!(companion.gender, gender);
+        assert!((game.date_in_game() - companion.date_of_birth).num_days() >= MIN_BREEDING_AGE_DAYS);
+        assert!(game.pedigree.get(id).is_some() && game.pedigree.get(companion.id).is_some());
+
+        // only the first time
+        game.set_dog(test_dog().0);
+        assert_eq!(game.scenes.iter().map(|scene| scene.residents.len()).sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn going_through_a_door_brings_the_dog_and_player_along() {
+        let mut game = test_game();
+        game.set_player(Player::new("sam".to_string(), Gender::Girl));
+        let (dog, sheet) = test_dog();
+        game.set_dog(dog);
+        game.set_state(GameState::Playing);
+        game.dog.as_mut().unwrap().food_level = Percent::new(42.0);
+
+        game.go_through("house", "spawn".to_string());
+        for _ in 0..TICKS_PER_SEC {
+            game.step_transition(1.0 / TICKS_PER_SEC as f32);
+        }
+
+        assert!(game.transition.is_none());
+        assert_eq!(game.scene().name, "house");
+        assert!(game.player.is_some());
+        assert_eq!(game.dog.as_ref().unwrap().food_level.as_f64(), 0.42);
+
+        // the companion is at home, so both dogs are drawn
+        let mut list = DrawList::new();
+        game.draw(&mut list);
+        let drawn = list.commands().iter()
+            .filter(|(_, command)| matches!(command, DrawCommand::Texture { texture, .. } if Arc::ptr_eq(texture, &sheet)))
+            .count();
+        assert_eq!(drawn, 2);
+    }
+
+    #[test]
+    fn dogs_next_to_each_other_can_have_puppies() {
+        let mut game = test_game();
+        let (mut dog, _) = test_dog();
+        dog.date_of_birth = START_DATE - chrono::TimeDelta::days(2 * 365);
+        game.set_dog(dog);
+
+        // too far from anyone out in the yard
+        let message = game.breed_with_nearest().unwrap();
+        assert!(message.contains("nobody close by"), "{}", message);
+
+        game.current_scene = game.scenes.iter().position(|scene| scene.name == COMPANION_HOME).unwrap();
+        let companion = &game.scene().residents[0];
+        let (companion_id, at) = (companion.id, companion.position);
+        game.dog.as_mut().unwrap().position = Vector2::new(at.x + 10.0, at.y);
+
+        let message = game.breed_with_nearest().unwrap();
+        assert!(message.ends_with("puppies!"), "{}", message);
+        let puppies = &game.scene().residents[1..];
+        let litter = puppies.len();
+        assert!(litter > 0);
+        let dog_id = game.dog.as_ref().unwrap().id;
+        for puppy in puppies {
+            assert_eq!(puppy.date_of_birth, game.date_in_game());
+            assert!([puppy.lineage.sire, puppy.lineage.dam].contains(&Some(companion_id)));
+            assert!([puppy.lineage.sire, puppy.lineage.dam].contains(&Some(dog_id)));
+            assert!(game.pedigree.get(puppy.id).is_some());
+        }
+
+        // the mum needs a rest before the next litter
+        let message = game.breed_with_nearest().unwrap();
+        assert!(message.contains("not long ago"), "{}", message);
+        assert_eq!(game.scene().residents.len(), 1 + litter);
+    }
+
+    #[test]
+    fn one_enter_press_opens_the_shelter_without_adopting() {
+        let mut game = test_game();
+        game.show_main_menu();
+
+        // two ticks in the same frame
+        let mut input = Input::with_keys(&[KeyboardKey::KEY_ENTER], &[]);
+        game.update(&input);
+        input.next_tick();
+        game.update(&input);
+
+        assert!(matches!(game.state, GameState::Shelter(_)));
+        assert!(game.take_adoption().is_none());
+
+        // a second press, in a later frame, adopts
+        game.update(&Input::with_keys(&[KeyboardKey::KEY_ENTER], &[]));
+        assert!(matches!(game.state, GameState::Playing));
+        assert!(game.take_adoption().is_some());
+    }
+}
@@ -0,0 +1,345 @@
+use crate::dog::DogBreed;
+use crate::rng::Rng;
+
+/// An allele that can be expressed. Higher rank wins over lower rank
+/// (simple complete dominance).
+pub trait Allele: Copy {
+    fn dominance(&self) -> u8;
+}
+
+/// Base coat color.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CoatColor {
+    Black,
+    Brown,
+    Golden,
+    Cream,
+    White,
+}
+
+/// Markings layered over the base color.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CoatPattern {
+    Merle,
+    Brindle,
+    Solid,
+    Tricolor,
+    Spotted,
+}
+
+/// Hair length / texture.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CoatLength {
+    Curly,
+    Short,
+    Medium,
+    Long,
+}
+
+/// Adult body size.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum DogSize {
+    Toy,
+    Small,
+    Medium,
+    Large,
+    Giant,
+}
+
+impl Allele for CoatColor {
+    fn dominance(&self) -> u8 {
+        match self {
+            CoatColor::Black => 4,
+            CoatColor::Brown => 3,
+            CoatColor::Golden => 2,
+            CoatColor::Cream => 1,
+            CoatColor::White => 0,
+        }
+    }
+}
+
+impl Allele for CoatPattern {
+    fn dominance(&self) -> u8 {
+        match self {
+            CoatPattern::Merle => 4,
+            CoatPattern::Brindle => 3,
+            CoatPattern::Solid => 2,
+            CoatPattern::Tricolor => 1,
+            CoatPattern::Spotted => 0,
+        }
+    }
+}
+
+impl Allele for CoatLength {
+    fn dominance(&self) -> u8 {
+        match self {
+            CoatLength::Curly => 3,
+            CoatLength::Short => 2,
+            CoatLength::Medium => 1,
+            CoatLength::Long => 0,
+        }
+    }
+}
+
+impl DogSize {
+    const ALL: [DogSize; 5] = [DogSize::Toy, DogSize::Small, DogSize::Medium, DogSize::Large, DogSize::Giant];
+
+    fn from_index(i: i64) -> Self {
+        Self::ALL[i.clamp(0, 4) as usize]
+    }
+
+    /// How many puppies a dam of this size usually has (min, max inclusive)
+    pub fn litter_range(&self) -> (i64, i64) {
+        match self {
+            DogSize::Toy => (1, 3),
+            DogSize::Small => (2, 5),
+            DogSize::Medium => (3, 7),
+            DogSize::Large => (5, 9),
+            DogSize::Giant => (6, 10),
+        }
+    }
+}
+
+/// Two copies of a gene, one from each parent.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GenePair<T> {
+    pub a: T,
+    pub b: T,
+}
+
+impl<T: Copy> GenePair<T> {
+    pub fn homozygous(value: T) -> Self {
+        Self { a: value, b: value }
+    }
+
+    /// Pass one of the two copies on to a puppy
+    pub fn contribute(&self, rng: &mut Rng) -> T {
+        if rng.chance(0.5) { self.a } else { self.b }
+    }
+}
+
+impl<T: Allele> GenePair<T> {
+    pub fn express(&self) -> T {
+        if self.a.dominance() >= self.b.dominance() { self.a } else { self.b }
+    }
+}
+
+impl GenePair<DogSize> {
+    // size is polygenic, so the two copies blend instead of one dominating
+    pub fn express(&self) -> DogSize {
+        let sum = self.a as i64 + self.b as i64;
+        DogSize::from_index((sum + 1) / 2)
+    }
+}
+
+/// Temperament, each trait 0 - 100.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Personality {
+    pub energy: u8,
+    pub sociability: u8,
+    pub trainability: u8,
+    pub boldness: u8,
+}
+
+impl Personality {
+    pub fn new(energy: u8, sociability: u8, trainability: u8, boldness: u8) -> Self {
+        Self { energy, sociability, trainability, boldness }
+    }
+
+    /// Midparent value plus a bit of noise for every trait
+    pub fn inherit(sire: &Personality, dam: &Personality, rng: &mut Rng) -> Self {
+        let mut blend = |a: u8, b: u8| -> u8 {
+            let mid = (a as i64 + b as i64) / 2;
+            (mid + rng.range(-10, 11)).clamp(0, 100) as u8
+        };
+
+        Self {
+            energy: blend(sire.energy, dam.energy),
+            sociability: blend(sire.sociability, dam.sociability),
+            trainability: blend(sire.trainability, dam.trainability),
+            boldness: blend(sire.boldness, dam.boldness),
+        }
+    }
+}
+
+/// The expressed coat
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Coat {
+    pub color: CoatColor,
+    pub pattern: CoatPattern,
+    pub length: CoatLength,
+}
+
+/// Everything a dog passes on to its puppies (apart from breed, which is
+/// worked out from both parents' breeds by `mix_breeds`).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Genome {
+    pub color: GenePair<CoatColor>,
+    pub pattern: GenePair<CoatPattern>,
+    pub length: GenePair<CoatLength>,
+    pub size: GenePair<DogSize>,
+    pub personality: Personality,
+}
+
+impl Genome {
+    /// Breed-standard genome for a purebred (or founder) dog
+    pub fn for_breed(breed: DogBreed) -> Self {
+        let (color, pattern, length, size, personality) = breed_standard(breed);
+        Self {
+            color: GenePair::homozygous(color),
+            pattern: GenePair::homozygous(pattern),
+            length: GenePair::homozygous(length),
+            size: GenePair::homozygous(size),
+            personality,
+        }
+    }
+
+    pub fn cross(sire: &Genome, dam: &Genome, rng: &mut Rng) -> Self {
+        Self {
+            color: GenePair { a: sire.color.contribute(rng), b: dam.color.contribute(rng) },
+            pattern: GenePair { a: sire.pattern.contribute(rng), b: dam.pattern.contribute(rng) },
+            length: GenePair { a: sire.length.contribute(rng), b: dam.length.contribute(rng) },
+            size: GenePair { a: sire.size.contribute(rng), b: dam.size.contribute(rng) },
+            personality: Personality::inherit(&sire.personality, &dam.personality, rng),
+        }
+    }
+
+    pub fn coat(&self) -> Coat {
+        Coat {
+            color: self.color.express(),
+            pattern: self.pattern.express(),
+            length: self.length.express(),
+        }
+    }
+
+    pub fn size(&self) -> DogSize {
+        self.size.express()
+    }
+}
+
+/// Breed of a puppy given its parents' breeds
+pub fn mix_breeds(sire: DogBreed, dam: DogBreed) -> DogBreed {
+    if sire == dam {
+        return sire;
+    }
+
+    // designer crosses are poodle + something; a designer dog crossed back
+    // to either of its parent breeds keeps the designer name
+    let other = match (sire, dam) {
+        (DogBreed::Poodle, other) | (other, DogBreed::Poodle) => other,
+        (DogBreed::Labradoodle, DogBreed::Labrador) | (DogBreed::Labrador, DogBreed::Labradoodle) => return DogBreed::Labradoodle,
+        (DogBreed::Goldendoodle, DogBreed::GoldenRetriever) | (DogBreed::GoldenRetriever, DogBreed::Goldendoodle) => return DogBreed::Goldendoodle,
+        (DogBreed::Shepadoodle, DogBreed::GermanShepherd) | (DogBreed::GermanShepherd, DogBreed::Shepadoodle) => return DogBreed::Shepadoodle,
+        (DogBreed::Cockapoo, DogBreed::CavalierKingCharlesSpaniel) | (DogBreed::CavalierKingCharlesSpaniel, DogBreed::Cockapoo) => return DogBreed::Cockapoo,
+        _ => return DogBreed::Mutt,
+    };
+
+    match other {
+        DogBreed::Labrador | DogBreed::Labradoodle => DogBreed::Labradoodle,
+        DogBreed::GoldenRetriever | DogBreed::Goldendoodle => DogBreed::Goldendoodle,
+        DogBreed::GermanShepherd | DogBreed::Shepadoodle => DogBreed::Shepadoodle,
+        // the closest thing to a cocker spaniel we have
+        DogBreed::CavalierKingCharlesSpaniel | DogBreed::Cockapoo => DogBreed::Cockapoo,
+        _ => DogBreed::Mutt,
+    }
+}
+
+fn breed_standard(breed: DogBreed) -> (CoatColor, CoatPattern, CoatLength, DogSize, Personality) {
+    use CoatColor as C;
+    use CoatLength as L;
+    use CoatPattern as P;
+    use DogSize as S;
+
+    match breed {
+        DogBreed::Pitbull => (C::Brown, P::Solid, L::Short, S::Medium, Personality::new(75, 80, 60, 70)),
+        DogBreed::GermanShepherd => (C::Black, P::Tricolor, L::Medium, S::Large, Personality::new(75, 50, 90, 80)),
+        DogBreed::Corgie => (C::Golden, P::Tricolor, L::Medium, S::Small, Personality::new(70, 75, 70, 65)),
+        DogBreed::AustralianShepherd => (C::Black, P::Merle, L::Medium, S::Medium, Personality::new(95, 60, 90, 60)),
+        DogBreed::BorderCollie => (C::Black, P::Tricolor, L::Medium, S::Medium, Personality::new(100, 55, 100, 55)),
+        DogBreed::BloodHound => (C::Brown, P::Solid, L::Short, S::Large, Personality::new(50, 70, 40, 50)),
+        DogBreed::CavalierKingCharlesSpaniel => (C::Brown, P::Tricolor, L::Long, S::Small, Personality::new(45, 95, 65, 35)),
+        DogBreed::Havanese => (C::Cream, P::Solid, L::Long, S::Toy, Personality::new(55, 90, 70, 40)),
+        DogBreed::Husky => (C::Black, P::Tricolor, L::Medium, S::Large, Personality::new(95, 70, 35, 65)),
+        DogBreed::Mutt => (C::Brown, P::Solid, L::Short, S::Medium, Personality::new(60, 70, 60, 55)),
+        DogBreed::Poodle => (C::Cream, P::Solid, L::Curly, S::Medium, Personality::new(65, 70, 95, 50)),
+        DogBreed::Labradoodle => (C::Golden, P::Solid, L::Curly, S::Large, Personality::new(75, 90, 85, 55)),
+        DogBreed::Goldendoodle => (C::Golden, P::Solid, L::Curly, S::Large, Personality::new(70, 95, 85, 50)),
+        DogBreed::Shepadoodle => (C::Black, P::Tricolor, L::Curly, S::Large, Personality::new(75, 65, 95, 70)),
+        DogBreed::Cockapoo => (C::Cream, P::Solid, L::Curly, S::Small, Personality::new(65, 90, 80, 40)),
+        DogBreed::Schnauser => (C::Black, P::Solid, L::Medium, S::Small, Personality::new(70, 60, 70, 75)),
+        DogBreed::ScottishTerrier => (C::Black, P::Solid, L::Medium, S::Small, Personality::new(55, 40, 50, 85)),
+        DogBreed::Labrador => (C::Black, P::Solid, L::Short, S::Large, Personality::new(80, 95, 85, 55)),
+        DogBreed::GoldenRetriever => (C::Golden, P::Solid, L::Long, S::Large, Personality::new(75, 100, 90, 50)),
+        DogBreed::SaintBernard => (C::Brown, P::Tricolor, L::Long, S::Giant, Personality::new(35, 85, 60, 55)),
+        DogBreed::Greyhound => (C::Cream, P::Brindle, L::Short, S::Large, Personality::new(40, 65, 60, 35)),
+        DogBreed::GreatDane => (C::Black, P::Merle, L::Short, S::Giant, Personality::new(45, 80, 60, 60)),
+        DogBreed::Mastiff => (C::Golden, P::Brindle, L::Short, S::Giant, Personality::new(30, 65, 50, 80)),
+        DogBreed::Dalmatian => (C::White, P::Spotted, L::Short, S::Large, Personality::new(90, 70, 65, 60)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_same_seed_crosses_the_same_way() {
+        let sire = Genome::for_breed(DogBreed::Dalmatian);
+        let dam = Genome::for_breed(DogBreed::Poodle);
+
+        for seed in 0..20 {
+            let first = Genome::cross(&sire, &dam, &mut Rng::new(seed));
+            let again = Genome::cross(&sire, &dam, &mut Rng::new(seed));
+            assert_eq!(first, again, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn each_parent_passes_on_one_copy() {
+        let sire = Genome::for_breed(DogBreed::Labrador);
+        let dam = Genome::for_breed(DogBreed::Dalmatian);
+        let mut rng = Rng::new(7);
+
+        for _ in 0..20 {
+            let puppy = Genome::cross(&sire, &dam, &mut rng);
+            assert_eq!(puppy.color, GenePair { a: CoatColor::Black, b: CoatColor::White });
+            assert_eq!(puppy.pattern, GenePair { a: CoatPattern::Solid, b: CoatPattern::Spotted });
+            // black is dominant over white, solid over spotted
+            assert_eq!(puppy.coat().color, CoatColor::Black);
+            assert_eq!(puppy.coat().pattern, CoatPattern::Solid);
+            assert_eq!(puppy.size(), DogSize::Large);
+        }
+    }
+
+    #[test]
+    fn purebreds_breed_true() {
+        let parent = Genome::for_breed(DogBreed::GoldenRetriever);
+        let mut rng = Rng::new(3);
+
+        for _ in 0..20 {
+            let puppy = Genome::cross(&parent, &parent, &mut rng);
+            assert_eq!((puppy.color, puppy.pattern, puppy.length, puppy.size), (parent.color, parent.pattern, parent.length, parent.size));
+        }
+    }
+
+    #[test]
+    fn sizes_blend_and_personality_lands_near_the_middle() {
+        let sire = Genome::for_breed(DogBreed::GreatDane);
+        let dam = Genome::for_breed(DogBreed::Havanese);
+        let mut rng = Rng::new(11);
+
+        for _ in 0..50 {
+            let puppy = Genome::cross(&sire, &dam, &mut rng);
+            // giant and toy meet in the middle
+            assert_eq!(puppy.size(), DogSize::Medium);
+
+            let mid = |a: u8, b: u8| (a as i64 + b as i64) / 2;
+            let near = |trait_: u8, a: u8, b: u8| (trait_ as i64 - mid(a, b)).abs() <= 10;
+            let (s, d, p) = (sire.personality, dam.personality, puppy.personality);
+            assert!(near(p.energy, s.energy, d.energy));
+            assert!(near(p.sociability, s.sociability, d.sociability));
+            assert!(near(p.trainability, s.trainability, d.trainability));
+            assert!(near(p.boldness, s.boldness, d.boldness));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::screen::Screen;
+use raylib::prelude::{KeyboardKey, MouseButton, RaylibHandle, Vector2};
+
+// every key the game reacts to, see CONTROLS.md
+const KEYS: [KeyboardKey; 15] = [
+    KeyboardKey::KEY_Q,
+    KeyboardKey::KEY_ENTER,
+    KeyboardKey::KEY_SPACE,
+    KeyboardKey::KEY_UP,
+    KeyboardKey::KEY_DOWN,
+    KeyboardKey::KEY_LEFT,
+    KeyboardKey::KEY_RIGHT,
+    KeyboardKey::KEY_EQUAL,
+    KeyboardKey::KEY_MINUS,
+    KeyboardKey::KEY_F,
+    KeyboardKey::KEY_B,
+    KeyboardKey::KEY_P,
+    KeyboardKey::KEY_M,
+    KeyboardKey::KEY_S,
+    KeyboardKey::KEY_L,
+];
+
+/// The keyboard as it was at the start of a frame. A frame can run several
+/// game ticks (or none), and raylib says a key is "pressed" for the whole
+/// frame, so presses are handed to the first tick only.
+#[derive(Clone, Debug, Default)]
+pub struct Input {
+    pressed: Vec<KeyboardKey>,
+    down: Vec<KeyboardKey>,
+    // left button, on the virtual screen
+    click: Option<Vector2>,
+}
+
+impl Input {
+    pub fn read(rl: &RaylibHandle, screen: &Screen) -> Self {
+        Self {
+            pressed: KEYS.iter().copied().filter(|key| rl.is_key_pressed(*key)).collect(),
+            down: KEYS.iter().copied().filter(|key| rl.is_key_down(*key)).collect(),
+            click: rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT).then(|| screen.to_virtual(rl.get_mouse_position())),
+        }
+    }
+
+    /// Keys pressed this frame and held ones, for tests
+    #[cfg(test)]
+    pub fn with_keys(pressed: &[KeyboardKey], down: &[KeyboardKey]) -> Self {
+        Self { pressed: pressed.to_vec(), down: down.to_vec(), click: None }
+    }
+
+    /// A left click at `at` on the virtual screen, for tests
+    #[cfg(test)]
+    pub fn with_click(at: Vector2) -> Self {
+        Self { click: Some(at), ..Self::default() }
+    }
+
+    /// Went down since the last frame, and no tick has seen it yet
+    pub fn pressed(&self, key: KeyboardKey) -> bool {
+        self.pressed.contains(&key)
+    }
+
+    /// Held down, for as long as it is
+    pub fn down(&self, key: KeyboardKey) -> bool {
+        self.down.contains(&key)
+    }
+
+    /// Where the left button went down on the virtual screen, like `pressed`
+    pub fn click(&self) -> Option<Vector2> {
+        self.click
+    }
+
+    /// Call after each tick, so the next one in the same frame doesn't see
+    /// the same presses again
+    pub fn next_tick(&mut self) {
+        self.pressed.clear();
+        self.click = None;
+    }
+}
@@ -1,28 +1,57 @@
 // use raylib::prelude::{RaylibDraw, RaylibDrawHandle};
+use raylib::prelude::KeyboardKey;
 use std::time::{Instant, Duration};
 use std::thread;
 
 mod animation;
+mod animator;
+mod aseprite;
+mod assets;
+mod breeding;
+mod camera;
+mod collision;
+mod daylight;
 mod game;
 mod dog;
+mod draw;
+mod genetics;
+mod input;
+mod manifest;
+mod parallax;
+mod particles;
 mod types;
 mod player;
+mod render;
+mod rng;
+mod scene;
+mod screen;
+mod shelter;
+mod state_machine;
+mod tilemap;
+mod tmx;
+mod weather;
 
 const TICKS_PER_SEC: i32 = 60;
 
 
-fn main() {
+fn main() -> anyhow::Result<()> {
     // Setup game window
     let (mut rl, thread) = raylib::init()
-        .size(800, 600)
+        .size(1280, 720)
         .title("All My Doggies")
+        .resizable()
         .build();
+    rl.set_window_min_size(screen::VIRTUAL_H * 4 / 3, screen::VIRTUAL_H);
+
+    // everything is drawn at a fixed height and scaled up to the window
+    let mut screen = screen::Screen::new(&mut rl, &thread)?;
 
-    let scottie = dog::Dog::new(&mut rl, &thread, "Scottie".to_string(), dog::DogBreed::Dalmatian, types::Gender::Boy, chrono::NaiveDate::from_ymd_opt(2023, 11, 14).unwrap());
     let player = player::Player::new("noah".to_string(), types::Gender::Boy);
 
     // Setup game data struct
-    let mut game = game::Game::new(&mut rl, &thread);
+    let mut game = game::Game::new(&mut rl, &thread, screen.width(), screen.height())?;
+    // what the game draws each frame, reused so it doesn't allocate every time
+    let mut frame = draw::DrawList::new();
 
     // Setup game timer
     let step = Duration::from_secs_f64(1.0 / TICKS_PER_SEC as f64);
@@ -32,8 +61,7 @@ fn main() {
 
     game.show_splash();
 
-    // these are just temp
-    game.set_dog(scottie);
+    // this is just temp
     game.set_player(player);
 
     while !rl.window_should_close() {
@@ -47,9 +75,11 @@ fn main() {
         let clamped = if frame_dt > max_frame { max_frame } else { frame_dt };
         accumulator += clamped;
 
-        // fixed updates
+        // fixed updates. a key press counts once, however many ticks this frame runs
+        let mut input = input::Input::read(&rl, &screen);
         while accumulator >= step {
-            game.update(&rl);
+            game.update(&input);
+            input.next_tick();
             accumulator -= step;
         }
 
@@ -57,8 +87,24 @@ fn main() {
             break;
         }
 
+        // loading the adopted dog's sprites needs the raylib handle, which the game doesn't own
+        if let Some(candidate) = game.take_adoption() {
+            let dog = candidate.into_dog(&mut game.assets, &mut rl, &thread);
+            game.set_dog(dog);
+        }
+
+        if rl.is_key_pressed(KeyboardKey::KEY_F11) {
+            screen.toggle_fullscreen(&mut rl);
+        }
+        if screen.update(&mut rl, &thread)? {
+            game.resize(screen.width(), screen.height());
+        }
+
+        // the game writes the frame down, then it's drawn for real
+        frame.clear();
+        game.draw(&mut frame);
         let mut d = rl.begin_drawing(&thread); // or however you begin your frame
-        game.draw(&mut d);
+        screen.present(&mut d, &thread, |d| frame.execute(d));
 
         // pace to next tick boundary (optional but nice)
         next_tick += step;
@@ -79,4 +125,6 @@ fn main() {
             next_tick = Instant::now();
         }
     }
+
+    Ok(())
 }
@@ -0,0 +1,398 @@
+use crate::animation::{AnimationBank, AnimationDescriptor, AnimationKey, Emotion, Facing, PlaybackMode, Pose, SheetGrid};
+use crate::aseprite;
+use crate::assets::Assets;
+use crate::state_machine::Transition;
+use raylib::prelude::{Rectangle, Texture2D, Vector2};
+use raylib::{RaylibHandle, RaylibThread};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{bail, Context};
+
+const DEFAULT_FRAME_MS: u32 = 100;
+
+/// A sprite set described in JSON, e.g. images/pixeldoggies/manifest.json.
+/// Paths inside are relative to the manifest file.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SpriteManifest {
+    #[serde(default)]
+    pub clips: Vec<ClipManifest>,
+    // Aseprite JSON exports whose tags become extra clips, see aseprite.rs
+    #[serde(default)]
+    pub aseprite: Vec<String>,
+    #[serde(default)]
+    pub bindings: Vec<BindingManifest>,
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+/// One named clip. Frames come from either a `grid` or explicit `rects`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ClipManifest {
+    pub name: String,
+    pub sheet: String,
+    #[serde(default)]
+    pub grid: Option<GridManifest>,
+    #[serde(default)]
+    pub rects: Vec<[f32; 4]>, // x, y, w, h
+    #[serde(default = "default_frame_ms")]
+    pub frame_ms: u32,
+    // per-frame timings, overrides frame_ms. one entry per frame
+    #[serde(default)]
+    pub durations_ms: Vec<u32>,
+    #[serde(default = "default_true")]
+    pub looped: bool,
+    #[serde(default = "default_mode")]
+    pub mode: PlaybackMode,
+    #[serde(default = "default_true")]
+    pub mirrorable: bool,
+    #[serde(default)]
+    pub pivot: Option<[f32; 2]>, // in frame pixels
+    #[serde(default)]
+    pub events: Vec<EventManifest>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GridManifest {
+    pub frame_width: i32,
+    pub frame_height: i32,
+    #[serde(default)]
+    pub margin: i32,
+    #[serde(default)]
+    pub spacing: i32,
+    #[serde(default)]
+    pub rows: Option<[usize; 2]>,    // start, end (exclusive)
+    #[serde(default)]
+    pub columns: Option<[usize; 2]>, // start, end (exclusive)
+    // how many frames the sheet is expected to hold. checked against the
+    // image so a wrong count fails at load instead of cutting garbage
+    #[serde(default)]
+    pub frames: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EventManifest {
+    pub frame: usize,
+    pub name: String,
+}
+
+/// Use a clip for a pose/emotion/facing, see `AnimationBank::bind`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BindingManifest {
+    pub pose: Pose,
+    #[serde(default = "default_emotion")]
+    pub emotion: Emotion,
+    #[serde(default = "default_facing")]
+    pub facing: Facing,
+    pub clip: String,
+}
+
+fn default_frame_ms() -> u32 { DEFAULT_FRAME_MS }
+fn default_true() -> bool { true }
+fn default_mode() -> PlaybackMode { PlaybackMode::Forward }
+fn default_emotion() -> Emotion { Emotion::Neutral }
+fn default_facing() -> Facing { Facing::Right }
+
+impl GridManifest {
+    pub fn to_grid(&self) -> SheetGrid {
+        let mut grid = SheetGrid::new(self.frame_width, self.frame_height)
+            .with_margin(self.margin)
+            .with_spacing(self.spacing);
+        if let Some([start, end]) = self.rows {
+            grid = grid.with_rows(start..end);
+        }
+        if let Some([start, end]) = self.columns {
+            grid = grid.with_columns(start..end);
+        }
+        grid
+    }
+}
+
+impl ClipManifest {
+    /// Source rects for this clip on a sheet of the given size
+    pub fn frames(&self, sheet_width: i32, sheet_height: i32) -> anyhow::Result<Vec<Rectangle>> {
+        let rects: Vec<Rectangle> = match &self.grid {
+            Some(grid) => {
+                let rects = grid.to_grid().frames(sheet_width, sheet_height)?;
+                if let Some(expected) = grid.frames && rects.len() != expected {
+                    bail!(
+                        "expected {} frames of {}x{} but the {}x{} sheet holds {}",
+                        expected, grid.frame_width, grid.frame_height, sheet_width, sheet_height, rects.len()
+                    );
+                }
+                // leftover pixels usually mean the frame size is wrong
+                let used_w = 2 * grid.margin + grid.to_grid().column_count(sheet_width) as i32 * (grid.frame_width + grid.spacing) - grid.spacing;
+                if grid.columns.is_none() && used_w != sheet_width {
+                    bail!("{}px wide frames don't evenly split the {}px wide sheet", grid.frame_width, sheet_width);
+                }
+                rects
+            },
+            None => self.rects.iter().map(|r| Rectangle::new(r[0], r[1], r[2], r[3])).collect(),
+        };
+
+        for (i, r) in rects.iter().enumerate() {
+            if r.x < 0.0 || r.y < 0.0 || r.x + r.width > sheet_width as f32 || r.y + r.height > sheet_height as f32 {
+                bail!("frame {} {:?} hangs off the {}x{} sheet", i, r, sheet_width, sheet_height);
+            }
+        }
+
+        Ok(rects)
+    }
+
+    /// Checks that don't need the image. Returns every problem found.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        match (&self.grid, self.rects.is_empty()) {
+            (Some(_), false) => problems.push("has both a grid and rects".to_string()),
+            (None, true) => problems.push("needs a grid or rects".to_string()),
+            _ => {},
+        }
+        if let Some(grid) = &self.grid && (grid.frame_width <= 0 || grid.frame_height <= 0) {
+            problems.push(format!("frame size {}x{} must be positive", grid.frame_width, grid.frame_height));
+        }
+        if self.rects.iter().any(|r| r[2] <= 0.0 || r[3] <= 0.0) {
+            problems.push("rects must have a positive size".to_string());
+        }
+
+        if self.frame_ms == 0 || self.durations_ms.contains(&0) {
+            problems.push("frame durations must be above 0ms".to_string());
+        }
+
+        // the rest needs the frame count, which for a grid without `frames` only the image knows
+        let frame_count = match &self.grid {
+            Some(grid) => grid.frames,
+            None => Some(self.rects.len()),
+        };
+        if let Some(count) = frame_count {
+            if !self.durations_ms.is_empty() && self.durations_ms.len() != count {
+                problems.push(format!("{} durations for {} frames", self.durations_ms.len(), count));
+            }
+            for event in &self.events {
+                if event.frame >= count {
+                    problems.push(format!("event '{}' is on frame {} but there are only {} frames", event.name, event.frame, count));
+                }
+            }
+        }
+
+        let frame_size = match &self.grid {
+            Some(grid) => Some((grid.frame_width as f32, grid.frame_height as f32)),
+            None => self.rects.first().map(|r| (r[2], r[3])),
+        };
+        if let (Some([x, y]), Some((w, h))) = (self.pivot, frame_size) && (x < 0.0 || y < 0.0 || x > w || y > h) {
+            problems.push(format!("pivot ({}, {}) is outside the {}x{} frame", x, y, w, h));
+        }
+
+        problems
+    }
+
+    /// Builds the descriptor once the sheet is loaded
+    pub fn build(&self, texture: Arc<Texture2D>) -> anyhow::Result<AnimationDescriptor> {
+        let frames = self.frames(texture.width, texture.height)?;
+        let count = frames.len();
+
+        if !self.durations_ms.is_empty() && self.durations_ms.len() != count {
+            bail!("{} durations for {} frames", self.durations_ms.len(), count);
+        }
+        if let Some(event) = self.events.iter().find(|e| e.frame >= count) {
+            bail!("event '{}' is on frame {} but there are only {} frames", event.name, event.frame, count);
+        }
+
+        let mut descriptor = AnimationDescriptor::new(texture, frames, self.frame_ms as f32 / 1000.0, self.looped)
+            .with_mode(self.mode)
+            .with_mirrorable(self.mirrorable);
+        if !self.durations_ms.is_empty() {
+            descriptor = descriptor.with_frame_durations(self.durations_ms.iter().map(|ms| *ms as f32 / 1000.0).collect());
+        }
+        if let Some([x, y]) = self.pivot {
+            descriptor = descriptor.with_pivot(Vector2::new(x, y));
+        }
+        for event in &self.events {
+            descriptor.add_event(event.frame, &event.name);
+        }
+
+        Ok(descriptor)
+    }
+}
+
+impl SpriteManifest {
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Reads the manifest and pulls in the clips of any Aseprite exports it lists.
+    /// Sheet paths come back relative to the working directory.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("reading sprite manifest {}", path))?;
+        let mut manifest = Self::parse(&json).with_context(|| format!("parsing sprite manifest {}", path))?;
+
+        let dir = Path::new(path).parent().unwrap_or(Path::new("")).to_path_buf();
+        for clip in &mut manifest.clips {
+            clip.sheet = relative_to(&dir, &clip.sheet);
+        }
+        for export in std::mem::take(&mut manifest.aseprite) {
+            let export_path = relative_to(&dir, &export);
+            let clips = aseprite::import(&export_path).with_context(|| format!("importing {} from {}", export_path, path))?;
+            manifest.clips.extend(clips);
+        }
+
+        manifest.validate().with_context(|| format!("invalid sprite manifest {}", path))?;
+        Ok(manifest)
+    }
+
+    /// Everything that can be checked without loading images: clip names,
+    /// bindings, timings, events and pivots. Lists every problem at once.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut problems = Vec::new();
+        let mut names = HashSet::new();
+
+        for clip in &self.clips {
+            if !names.insert(clip.name.as_str()) {
+                problems.push(format!("clip '{}' is declared twice", clip.name));
+            }
+            problems.extend(clip.problems().into_iter().map(|p| format!("clip '{}': {}", clip.name, p)));
+        }
+        for binding in &self.bindings {
+            if !names.contains(binding.clip.as_str()) {
+                problems.push(format!("{:?}/{:?}/{:?} is bound to unknown clip '{}'", binding.pose, binding.emotion, binding.facing, binding.clip));
+            }
+        }
+        for transition in &self.transitions {
+            if transition.from == transition.to {
+                problems.push(format!("transition from {:?} to itself", transition.from));
+            }
+            if let Some(clip) = &transition.clip && !names.contains(clip.as_str()) {
+                problems.push(format!("transition {:?} -> {:?} uses unknown clip '{}'", transition.from, transition.to, clip));
+            }
+        }
+
+        if !problems.is_empty() {
+            bail!("{}", problems.join("; "));
+        }
+        Ok(())
+    }
+
+    /// Fills a bank with the clips and bindings. Sheets come from `assets`,
+    /// so clips cut from the same image share one texture.
+    pub fn into_bank(self, assets: &mut Assets, rl: &mut RaylibHandle, thread: &RaylibThread) -> anyhow::Result<AnimationBank> {
+        let mut bank = AnimationBank::new();
+
+        for clip in &self.clips {
+            let texture = assets.texture(rl, thread, &clip.sheet)
+                .with_context(|| format!("loading sheet for clip '{}'", clip.name))?;
+
+            let descriptor = clip.build(texture).with_context(|| format!("clip '{}' ({})", clip.name, clip.sheet))?;
+            bank.insert_clip(&clip.name, descriptor);
+        }
+
+        for binding in &self.bindings {
+            bank.bind(AnimationKey { pose: binding.pose, emotion: binding.emotion, facing: binding.facing }, &binding.clip);
+        }
+        bank.graph.transitions = self.transitions;
+
+        Ok(bank)
+    }
+}
+
+pub(crate) fn relative_to(dir: &Path, path: &str) -> String {
+    let joined: PathBuf = dir.join(path);
+    joined.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poses_arent_bound_to_transition_clips() {
+        // a pose's loop that is also a transition would replay the transition
+        // whenever its base clip restarts, e.g. after a bark
+        let manifest = SpriteManifest::load("images/pixeldoggies/manifest.json").unwrap();
+        for binding in &manifest.bindings {
+            let clip = manifest.clips.iter().find(|clip| clip.name == binding.clip).unwrap();
+            assert!(clip.looped, "{:?} is bound to '{}', which plays once", binding.pose, clip.name);
+            assert!(
+                manifest.transitions.iter().all(|t| t.clip.as_deref() != Some(binding.clip.as_str())),
+                "{:?} is bound to '{}', which is also a transition", binding.pose, clip.name,
+            );
+        }
+    }
+
+    const GOOD: &str = r#"{
+        "clips": [
+            {
+                "name": "idle",
+                "sheet": "IdleDog.png",
+                "grid": { "frame_width": 64, "frame_height": 64, "frames": 4 },
+                "pivot": [30, 64],
+                "events": [ { "frame": 3, "name": "blink" } ]
+            },
+            {
+                "name": "wag",
+                "sheet": "Wag.png",
+                "rects": [ [0, 0, 32, 32], [32, 0, 32, 32] ],
+                "durations_ms": [ 100, 300 ],
+                "looped": false
+            }
+        ],
+        "bindings": [ { "pose": "standing", "clip": "idle" }, { "pose": "sitting", "facing": "left", "clip": "wag" } ],
+        "transitions": [ { "from": "standing", "to": "sitting", "clip": "wag" } ]
+    }"#;
+
+    fn problems_in(json: &str) -> String {
+        let manifest = SpriteManifest::parse(json).unwrap();
+        format!("{:#}", manifest.validate().unwrap_err())
+    }
+
+    #[test]
+    fn a_good_manifest_builds() {
+        let manifest = SpriteManifest::parse(GOOD).unwrap();
+        manifest.validate().unwrap();
+        assert_eq!(manifest.bindings[1].facing, Facing::Left);
+        assert_eq!(manifest.bindings[1].emotion, Emotion::Neutral);
+        assert_eq!(manifest.transitions[0].to, Pose::Sitting);
+
+        let idle = manifest.clips[0].build(crate::draw::test_sheet(256, 64)).unwrap();
+        assert_eq!(idle.frames.len(), 4);
+        assert_eq!(idle.frames[3], Rectangle::new(192.0, 0.0, 64.0, 64.0));
+        assert_eq!(idle.frame_duration(0), DEFAULT_FRAME_MS as f32 / 1000.0);
+        assert!(idle.looped);
+
+        let wag = manifest.clips[1].build(crate::draw::test_sheet(64, 32)).unwrap();
+        assert_eq!((wag.frame_duration(0), wag.frame_duration(1)), (0.1, 0.3));
+        assert!(!wag.looped);
+    }
+
+    #[test]
+    fn a_binding_to_an_unknown_clip_is_reported() {
+        let json = GOOD.replace(r#"{ "pose": "standing", "clip": "idle" }"#, r#"{ "pose": "standing", "clip": "idel" }"#);
+        let problems = problems_in(&json);
+        assert!(problems.contains("bound to unknown clip 'idel'"), "{}", problems);
+    }
+
+    #[test]
+    fn an_event_past_the_last_frame_is_reported() {
+        let json = GOOD.replace(r#""frame": 3"#, r#""frame": 4"#);
+        let problems = problems_in(&json);
+        assert!(problems.contains("clip 'idle': event 'blink' is on frame 4 but there are only 4 frames"), "{}", problems);
+
+        // without a frame count only the image can tell, so it's caught when building
+        let json = json.replace(r#", "frames": 4"#, "");
+        let manifest = SpriteManifest::parse(&json).unwrap();
+        manifest.validate().unwrap();
+        let Err(err) = manifest.clips[0].build(crate::draw::test_sheet(256, 64)) else { panic!("built with an event past the end") };
+        assert!(err.to_string().contains("only 4 frames"), "{}", err);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let json = GOOD.replace(r#""looped": false"#, r#""loop": false"#);
+        assert!(SpriteManifest::parse(&json).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::animation::Sheet;
+use crate::assets::Assets;
+use crate::draw::DrawList;
+use raylib::prelude::{Camera2D, Rectangle, Vector2};
+use raylib::color::Color;
+use raylib::{RaylibHandle, RaylibThread};
+use std::path::Path;
+use anyhow::{bail, Context};
+
+// how far the furthest and nearest layers move for each pixel the camera moves.
+// the ones between are spread evenly
+const FAR_FACTOR: f32 = 0.05;
+const NEAR_FACTOR: f32 = 0.5;
+
+/// Folder of one of the layered forest backdrops, 1 to 8
+pub fn forest_dir(set: u32) -> String {
+    format!("images/forest_trees/{}", set)
+}
+
+/// `1.png`, `2.png`... in `dir`, up to the first number that's missing.
+/// The forest sets also ship every layer flattened into `2304x1296.png`,
+/// which this never picks up.
+pub fn layer_paths(dir: &str) -> Vec<String> {
+    (1..)
+        .map(|n| format!("{}/{}.png", dir, n))
+        .take_while(|path| Path::new(path).exists())
+        .collect()
+}
+
+struct ParallaxLayer {
+    texture: Sheet,
+    // 0 stays put, 1 moves with the world
+    factor: f32,
+}
+
+/// A backdrop of stacked images that scroll slower the further away they
+/// are, drawn in screen space behind the map
+pub struct Parallax {
+    // furthest first
+    layers: Vec<ParallaxLayer>,
+}
+
+impl Parallax {
+    /// Loads `layer_paths(dir)`, furthest first
+    pub fn load(assets: &mut Assets, rl: &mut RaylibHandle, thread: &RaylibThread, dir: &str) -> anyhow::Result<Self> {
+        let paths = layer_paths(dir);
+        if paths.is_empty() {
+            bail!("{} has no parallax layers (needs 1.png, 2.png...)", dir);
+        }
+
+        let count = paths.len();
+        let mut layers = Vec::with_capacity(count);
+        for (i, path) in paths.iter().enumerate() {
+            let texture = assets.texture(rl, thread, path).with_context(|| format!("parallax layer {}", path))?;
+            let depth = if count > 1 { i as f32 / (count - 1) as f32 } else { 0.0 };
+            layers.push(ParallaxLayer { texture, factor: FAR_FACTOR + (NEAR_FACTOR - FAR_FACTOR) * depth });
+        }
+
+        Ok(Self { layers })
+    }
+
+    /// Covers the screen, scrolled sideways by how far `camera` has moved
+    /// from the world origin. Copies repeat mirrored, so any layer tiles
+    /// without a seam.
+    pub fn draw(&self, d: &mut DrawList, camera: &Camera2D, screen_w: f32, screen_h: f32) {
+        for layer in &self.layers {
+            let tex_w = layer.texture.width as f32;
+            let tex_h = layer.texture.height as f32;
+
+            // "cover" scaling, like the old single background
+            let scale = f32::max(screen_w / tex_w, screen_h / tex_h);
+            let (dest_w, dest_h) = (tex_w * scale, tex_h * scale);
+            let dest_y = ((screen_h - dest_h) * 0.5).round();
+
+            // centred when the camera looks at x = 0
+            let scroll = camera.target.x * camera.zoom * layer.factor;
+            let start = (screen_w - dest_w) * 0.5 - scroll;
+            let first = (-start / dest_w).floor() as i32;
+            let last = ((screen_w - start) / dest_w).ceil() as i32;
+
+            for copy in first..last {
+                let src_w = if copy.rem_euclid(2) == 0 { tex_w } else { -tex_w };
+                let dest = Rectangle::new((start + copy as f32 * dest_w).round(), dest_y, dest_w.ceil(), dest_h);
+                d.draw_texture_pro(&layer.texture, Rectangle::new(0.0, 0.0, src_w, tex_h), dest, Vector2::new(0.0, 0.0), 0.0, Color::WHITE);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_numbered_layer_is_used_and_the_flattened_one_isnt() {
+        // the yard's set: 1.png to 5.png plus 2304x1296.png
+        let paths = layer_paths(&forest_dir(8));
+        let expected: Vec<String> = (1..=5).map(|n| format!("images/forest_trees/8/{}.png", n)).collect();
+        assert_eq!(paths, expected);
+
+        for set in 1..=8 {
+            let paths = layer_paths(&forest_dir(set));
+            assert!(paths.len() >= 3, "set {} has {} layers", set, paths.len());
+            assert!(paths.iter().all(|path| !path.contains("2304x1296")));
+        }
+    }
+
+    #[test]
+    fn a_folder_without_layers_has_no_paths() {
+        assert!(layer_paths("images/forest_trees/nowhere").is_empty());
+    }
+}
@@ -0,0 +1,431 @@
+use crate::animator::AnimationSignal;
+use crate::dog::{Dog, DogId};
+use crate::draw::DrawList;
+use crate::rng::Rng;
+use raylib::prelude::Vector2;
+use raylib::color::Color;
+
+// how long a kept-alive emitter lingers after the last `keep_emitting`, in seconds
+const EMITTER_GRACE: f32 = 0.25;
+// from a pet's feet: over a sleeping head, and around the middle
+const SNORE_OFFSET: Vector2 = Vector2 { x: 6.0, y: -18.0 };
+const STINK_OFFSET: Vector2 = Vector2 { x: 0.0, y: -8.0 };
+// wetter ground than this and footsteps splash instead of raising dust
+const SPLASHY: f32 = 0.3;
+// muddier than this and a pet starts to smell
+const STINKY: f64 = 0.6;
+
+/// What a particle looks like
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shape {
+    Dot,
+    Heart,
+    Text(&'static str),
+    // a wavy line rising, for smells
+    Wisp,
+}
+
+/// How an emitter's particles start out and behave. Everything in map
+/// pixels and seconds; ranges are (min, max) and picked per particle.
+#[derive(Clone, Copy, Debug)]
+pub struct ParticleStyle {
+    // tells emitters apart
+    pub name: &'static str,
+    pub shape: Shape,
+    pub color: Color,
+    // particles per burst
+    pub burst: (u32, u32),
+    // particles a second while an emitter keeps going
+    pub rate: f32,
+    pub lifetime: (f32, f32),
+    pub size: (f32, f32),
+    // how much bigger (or smaller, below 1) it is by the end of its life
+    pub grow: f32,
+    // spread around the emitter when spawned
+    pub spread: Vector2,
+    pub velocity_x: (f32, f32),
+    pub velocity_y: (f32, f32),
+    // added to the velocity every second, positive is down
+    pub gravity: f32,
+}
+
+pub const HEARTS: ParticleStyle = ParticleStyle {
+    name: "hearts",
+    shape: Shape::Heart,
+    color: Color::new(240, 80, 120, 255),
+    burst: (3, 5),
+    rate: 0.0,
+    lifetime: (0.9, 1.4),
+    size: (2.0, 3.0),
+    grow: 1.3,
+    spread: Vector2 { x: 8.0, y: 4.0 },
+    velocity_x: (-8.0, 8.0),
+    velocity_y: (-30.0, -18.0),
+    gravity: 0.0,
+};
+
+pub const ZZZ: ParticleStyle = ParticleStyle {
+    name: "zzz",
+    shape: Shape::Text("z"),
+    color: Color::new(220, 230, 255, 255),
+    burst: (1, 1),
+    rate: 0.0,
+    lifetime: (1.6, 2.0),
+    size: (8.0, 8.0),
+    grow: 1.6,
+    spread: Vector2 { x: 2.0, y: 2.0 },
+    velocity_x: (4.0, 10.0),
+    velocity_y: (-14.0, -10.0),
+    gravity: 0.0,
+};
+
+pub const DUST: ParticleStyle = ParticleStyle {
+    name: "dust",
+    shape: Shape::Dot,
+    color: Color::new(200, 185, 160, 200),
+    burst: (2, 3),
+    rate: 0.0,
+    lifetime: (0.3, 0.5),
+    size: (1.0, 1.5),
+    grow: 2.5,
+    spread: Vector2 { x: 4.0, y: 1.0 },
+    velocity_x: (-10.0, 10.0),
+    velocity_y: (-8.0, -2.0),
+    gravity: 0.0,
+};
+
+pub const SPLASH: ParticleStyle = ParticleStyle {
+    name: "splash",
+    shape: Shape::Dot,
+    color: Color::new(150, 190, 240, 220),
+    burst: (4, 6),
+    rate: 0.0,
+    lifetime: (0.25, 0.4),
+    size: (0.6, 1.0),
+    grow: 1.0,
+    spread: Vector2 { x: 3.0, y: 1.0 },
+    velocity_x: (-24.0, 24.0),
+    velocity_y: (-40.0, -24.0),
+    gravity: 220.0,
+};
+
+pub const STINK: ParticleStyle = ParticleStyle {
+    name: "stink",
+    shape: Shape::Wisp,
+    color: Color::new(140, 170, 60, 200),
+    burst: (1, 1),
+    rate: 1.5,
+    lifetime: (1.2, 1.8),
+    size: (5.0, 7.0),
+    grow: 1.0,
+    spread: Vector2 { x: 10.0, y: 4.0 },
+    velocity_x: (-2.0, 2.0),
+    velocity_y: (-12.0, -8.0),
+    gravity: 0.0,
+};
+
+/// What an emitter is stuck to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Anchor {
+    // a fixed spot on the map, like a mess on the floor
+    World(Vector2),
+    // follows a pet around. the offset is from its feet
+    Pet(DogId, Vector2),
+}
+
+struct Particle {
+    shape: Shape,
+    color: Color,
+    position: Vector2,
+    velocity: Vector2,
+    gravity: f32,
+    age: f32,
+    lifetime: f32,
+    size: f32,
+    grow: f32,
+    // so wisps don't all wave together
+    phase: f32,
+}
+
+struct Emitter {
+    style: ParticleStyle,
+    anchor: Anchor,
+    // seconds left, topped up by keep_emitting
+    remaining: f32,
+    // part of a particle owed from earlier ticks
+    owed: f32,
+}
+
+/// Short-lived bits of effect: hearts, snores, dust... Each scene has its
+/// own, with a cap on how many particles can be alive at once. Once it's
+/// full new ones are skipped, so a busy scene can't slow the game down.
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    emitters: Vec<Emitter>,
+    cap: usize,
+    rng: Rng,
+}
+
+impl ParticleSystem {
+    pub fn new(cap: usize) -> Self {
+        Self { particles: Vec::new(), emitters: Vec::new(), cap, rng: Rng::from_time() }
+    }
+
+    /// Same, picking from `rng`, so tests can repeat
+    #[cfg(test)]
+    pub fn with_rng(cap: usize, rng: Rng) -> Self {
+        Self { particles: Vec::new(), emitters: Vec::new(), cap, rng }
+    }
+
+    /// One puff of `style` particles at a point on the map
+    pub fn burst(&mut self, style: &ParticleStyle, at: Vector2) {
+        let count = self.rng.range(style.burst.0 as i64, style.burst.1 as i64 + 1);
+        for _ in 0..count {
+            self.spawn(style, at);
+        }
+    }
+
+    /// Keeps an emitter going at `anchor` for as long as this is called
+    /// every tick. It stops shortly after the calls do.
+    pub fn keep_emitting(&mut self, style: &ParticleStyle, anchor: Anchor) {
+        match self.emitters.iter_mut().find(|emitter| emitter.anchor == anchor && emitter.style.name == style.name) {
+            Some(emitter) => emitter.remaining = EMITTER_GRACE,
+            None => self.emitters.push(Emitter { style: *style, anchor, remaining: EMITTER_GRACE, owed: 0.0 }),
+        }
+    }
+
+    /// Dust or splashes on a pet's footsteps, a "z" on each snore.
+    /// `feet` is where the pet stands.
+    pub fn animation_events(&mut self, signals: &[AnimationSignal], feet: Vector2, wetness: f32) {
+        for signal in signals {
+            let AnimationSignal::Event(name) = signal else { continue };
+            match name.as_str() {
+                "footstep" if wetness > SPLASHY => self.burst(&SPLASH, feet),
+                "footstep" => self.burst(&DUST, feet),
+                "snore" => self.burst(&ZZZ, Vector2::new(feet.x + SNORE_OFFSET.x, feet.y + SNORE_OFFSET.y)),
+                _ => {},
+            }
+        }
+    }
+
+    /// Keeps up whatever hangs around a pet in the state it's in, like
+    /// stink lines off a muddy one. Call every tick.
+    pub fn follow_pet(&mut self, dog: &Dog) {
+        if dog.mud_level.as_f64() > STINKY {
+            self.keep_emitting(&STINK, Anchor::Pet(dog.id, STINK_OFFSET));
+        }
+    }
+
+    /// `pets` are where the pets in the scene stand, for emitters stuck to
+    /// them. An emitter whose pet has gone stops.
+    pub fn update(&mut self, dt: f32, pets: &[(DogId, Vector2)]) {
+        for particle in &mut self.particles {
+            particle.age += dt;
+            particle.velocity.y += particle.gravity * dt;
+            particle.position.x += particle.velocity.x * dt;
+            particle.position.y += particle.velocity.y * dt;
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+
+        let mut emitters = std::mem::take(&mut self.emitters);
+        emitters.retain_mut(|emitter| {
+            emitter.remaining -= dt;
+            let at = match emitter.anchor {
+                Anchor::World(at) => Some(at),
+                Anchor::Pet(id, offset) => pets.iter()
+                    .find(|(pet, _)| *pet == id)
+                    .map(|(_, feet)| Vector2::new(feet.x + offset.x, feet.y + offset.y)),
+            };
+            let Some(at) = at else { return false };
+
+            emitter.owed += emitter.style.rate * dt;
+            while emitter.owed >= 1.0 {
+                emitter.owed -= 1.0;
+                self.spawn(&emitter.style, at);
+            }
+            emitter.remaining > 0.0
+        });
+        self.emitters = emitters;
+    }
+
+    fn spawn(&mut self, style: &ParticleStyle, at: Vector2) {
+        if self.particles.len() >= self.cap {
+            return;
+        }
+
+        let rng = &mut self.rng;
+        self.particles.push(Particle {
+            shape: style.shape,
+            color: style.color,
+            position: Vector2::new(
+                at.x + rng.range_f32(-style.spread.x, style.spread.x),
+                at.y + rng.range_f32(-style.spread.y, style.spread.y),
+            ),
+            velocity: Vector2::new(
+                rng.range_f32(style.velocity_x.0, style.velocity_x.1),
+                rng.range_f32(style.velocity_y.0, style.velocity_y.1),
+            ),
+            gravity: style.gravity,
+            age: 0.0,
+            lifetime: rng.range_f32(style.lifetime.0, style.lifetime.1).max(0.01),
+            size: rng.range_f32(style.size.0, style.size.1),
+            grow: style.grow,
+            phase: rng.range_f32(0.0, std::f32::consts::TAU),
+        });
+    }
+
+    /// Call inside the camera's 2D mode, after the map
+    pub fn draw(&self, d: &mut DrawList) {
+        for particle in &self.particles {
+            let t = particle.age / particle.lifetime;
+            let size = particle.size * (1.0 + (particle.grow - 1.0) * t);
+            // fade out over the second half of its life
+            let color = particle.color.fade(particle.color.a as f32 / 255.0 * (2.0 - 2.0 * t).min(1.0));
+            let Vector2 { x, y } = particle.position;
+
+            match particle.shape {
+                Shape::Dot => d.draw_circle_v(particle.position, size, color),
+                Shape::Heart => {
+                    // two bumps on top of a point
+                    let r = size * 0.5;
+                    d.draw_circle_v(Vector2::new(x - r, y), r, color);
+                    d.draw_circle_v(Vector2::new(x + r, y), r, color);
+                    d.draw_triangle(Vector2::new(x - size, y + r * 0.3), Vector2::new(x, y + size * 1.2), Vector2::new(x + size, y + r * 0.3), color);
+                },
+                Shape::Text(text) => d.draw_text(text, x.round() as i32, y.round() as i32, size.round() as i32, color),
+                Shape::Wisp => {
+                    let sway = |k: f32| (particle.age * 6.0 + particle.phase + k).sin() * 1.5;
+                    let step = size / 3.0;
+                    for i in 0..3 {
+                        let (y0, y1) = (y - step * i as f32, y - step * (i + 1) as f32);
+                        d.draw_line_v(Vector2::new(x + sway(i as f32), y0), Vector2::new(x + sway(i as f32 + 1.0), y1), color);
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 60.0;
+    const SPOT: Vector2 = Vector2 { x: 100.0, y: 50.0 };
+
+    fn particles(cap: usize) -> ParticleSystem {
+        ParticleSystem::with_rng(cap, Rng::new(7))
+    }
+
+    fn rgb(color: Color) -> (u8, u8, u8) {
+        (color.r, color.g, color.b)
+    }
+
+    fn footstep() -> Vec<AnimationSignal> {
+        vec![AnimationSignal::Event("footstep".to_string()), AnimationSignal::Finished("bark".to_string())]
+    }
+
+    #[test]
+    fn spawns_are_skipped_once_the_scene_is_full() {
+        let mut system = particles(10);
+        for _ in 0..10 {
+            system.burst(&HEARTS, SPOT);
+        }
+        assert_eq!(system.particles.len(), 10);
+
+        // room again once they've gone
+        system.update(HEARTS.lifetime.1, &[]);
+        assert!(system.particles.is_empty());
+        system.burst(&HEARTS, SPOT);
+        assert!((HEARTS.burst.0..=HEARTS.burst.1).contains(&(system.particles.len() as u32)));
+    }
+
+    #[test]
+    fn particles_go_at_the_end_of_their_lifetime() {
+        let mut system = particles(10);
+        system.burst(&ZZZ, SPOT);
+        let start = system.particles[0].position;
+
+        system.update(ZZZ.lifetime.0 - 0.1, &[]);
+        assert_eq!(system.particles.len(), 1);
+        // and drifted up on the way
+        assert!(system.particles[0].position.y < start.y);
+
+        system.update(ZZZ.lifetime.1 - ZZZ.lifetime.0 + 0.2, &[]);
+        assert!(system.particles.is_empty());
+    }
+
+    #[test]
+    fn an_emitter_per_anchor_and_style() {
+        let mut system = particles(100);
+        let muddy = Anchor::Pet(DogId(1), STINK_OFFSET);
+        system.keep_emitting(&STINK, muddy);
+        system.keep_emitting(&STINK, muddy);
+        assert_eq!(system.emitters.len(), 1);
+
+        system.keep_emitting(&STINK, Anchor::Pet(DogId(2), STINK_OFFSET));
+        system.keep_emitting(&HEARTS, muddy);
+        assert_eq!(system.emitters.len(), 3);
+    }
+
+    #[test]
+    fn emitters_stop_shortly_after_they_stop_being_kept() {
+        let mut system = particles(100);
+        let pets = [(DogId(1), SPOT)];
+        system.keep_emitting(&STINK, Anchor::Pet(DogId(1), STINK_OFFSET));
+
+        system.update(EMITTER_GRACE * 0.5, &pets);
+        assert_eq!(system.emitters.len(), 1);
+        system.update(EMITTER_GRACE * 0.6, &pets);
+        assert!(system.emitters.is_empty());
+
+        // or straight away when the pet isn't there
+        system.keep_emitting(&STINK, Anchor::Pet(DogId(1), STINK_OFFSET));
+        system.update(TICK, &[]);
+        assert!(system.emitters.is_empty());
+    }
+
+    #[test]
+    fn fractions_of_a_particle_add_up() {
+        // 1.5 a second, living long enough to count
+        let style = ParticleStyle { rate: 1.5, lifetime: (10.0, 10.0), ..STINK };
+        let mut system = particles(100);
+
+        // 0.1875 of a particle every eighth of a second
+        let mut counts = Vec::new();
+        for _ in 0..16 {
+            system.keep_emitting(&style, Anchor::World(SPOT));
+            system.update(0.125, &[]);
+            counts.push(system.particles.len());
+        }
+        assert_eq!((counts[4], counts[5], counts[10], counts[15]), (0, 1, 2, 3));
+    }
+
+    #[test]
+    fn footsteps_splash_on_wet_ground_and_raise_dust_on_dry() {
+        let mut system = particles(100);
+        system.animation_events(&footstep(), SPOT, SPLASHY + 0.1);
+        assert!(!system.particles.is_empty());
+        assert!(system.particles.iter().all(|particle| rgb(particle.color) == rgb(SPLASH.color)));
+
+        let mut system = particles(100);
+        system.animation_events(&footstep(), SPOT, SPLASHY);
+        assert!(!system.particles.is_empty());
+        assert!(system.particles.iter().all(|particle| rgb(particle.color) == rgb(DUST.color)));
+    }
+
+    #[test]
+    fn a_snore_lets_out_a_z() {
+        let mut system = particles(100);
+        system.animation_events(&[AnimationSignal::Event("snore".to_string())], SPOT, 0.0);
+        let [z] = &system.particles[..] else { panic!("{} particles", system.particles.len()) };
+        assert_eq!(z.shape, Shape::Text("z"));
+
+        // over the head, not at the feet
+        assert!(z.position.y < SPOT.y);
+
+        // other events don't make anything
+        system.animation_events(&[AnimationSignal::Event("bark".to_string()), AnimationSignal::TransitionFinished], SPOT, 1.0);
+        assert_eq!(system.particles.len(), 1);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::animation::AnimationPlayer;
+use crate::draw::DrawList;
+use crate::tilemap::{self, TileMap};
+use crate::tmx::{self, Gid, TmxTileLayer};
+use raylib::prelude::{Rectangle, Vector2};
+use raylib::color::Color;
+use std::collections::HashMap;
+
+/// How many world (map) pixels one art pixel covers. The camera zoom takes
+/// it from there to the screen.
+pub const WORLD_SCALE: f32 = 1.0;
+
+/// Scale rounded down to a whole number (at least 1), so every art pixel
+/// covers the same number of screen pixels and nothing shimmers
+pub fn pixel_scale(scale: f32) -> f32 {
+    scale.floor().max(1.0)
+}
+
+/// One animation frame placed in the world. `position` is where the clip's
+/// pivot (the feet, for the dogs) lands.
+pub struct Sprite<'a> {
+    pub player: &'a AnimationPlayer,
+    pub position: Vector2,
+    pub scale: f32,
+    pub tint: Color,
+}
+
+impl<'a> Sprite<'a> {
+    pub fn new(player: &'a AnimationPlayer, position: Vector2) -> Self {
+        Self { player, position, scale: WORLD_SCALE, tint: Color::WHITE }
+    }
+
+    /// Sort key: further down the screen is closer to the camera. Uses the
+    /// pivot, so a big dog and a small one standing side by side sort by their feet.
+    pub fn depth(&self) -> f32 {
+        self.position.y
+    }
+
+    /// Screen rect the frame covers, snapped to whole pixels
+    pub fn dest_rect(&self) -> Rectangle {
+        let scale = pixel_scale(self.scale);
+        let src = self.player.current_frame_rect();
+        let origin = self.player.origin();
+
+        Rectangle::new(
+            (self.position.x - origin.x * scale).round(),
+            (self.position.y - origin.y * scale).round(),
+            src.width.abs() * scale,
+            src.height * scale,
+        )
+    }
+
+    pub fn draw(&self, d: &mut DrawList) {
+        // the origin is already baked into dest_rect so the snapping applies to the corner
+        d.draw_texture_pro(
+            self.player.texture(),
+            self.player.source_rect(),
+            self.dest_rect(),
+            Vector2::new(0.0, 0.0),
+            0.0,
+            self.tint,
+        );
+    }
+}
+
+/// Draws back to front by pivot depth
+pub fn draw_sorted(d: &mut DrawList, sprites: &mut [Sprite]) {
+    sprites.sort_by(|a, b| a.depth().total_cmp(&b.depth()));
+    for sprite in sprites.iter() {
+        sprite.draw(d);
+    }
+}
+
+/// Tiled layer property that overrides `SortRules` for one layer: "true"
+/// y-sorts it with the sprites, "false" keeps it flat
+pub const SORT_PROPERTY: &str = "ysort";
+
+/// Tiled layer property for how a sorted layer's tiles find their depth:
+/// "object" (the default) sorts each group of touching tiles by the bottom
+/// of its lowest row, "tile" sorts every tile by its own bottom edge. Use
+/// "tile" for walls, which are all one block around a room and would
+/// otherwise sort as one thing by the front wall.
+pub const BASE_PROPERTY: &str = "base";
+
+/// Which map layers get y-sorted with the sprites, by name pattern (see `tmx::name_matches`)
+pub struct SortRules {
+    names: Vec<String>,
+}
+
+impl SortRules {
+    pub fn new(names: &[&str]) -> Self {
+        Self { names: names.iter().map(|name| name.to_string()).collect() }
+    }
+
+    pub fn sorts(&self, layer: &TmxTileLayer) -> bool {
+        if let Some(sorts) = tmx::flag(&layer.properties, SORT_PROPERTY) {
+            return sorts;
+        }
+
+        self.names.iter().any(|pattern| tmx::name_matches(pattern, &layer.name))
+    }
+}
+
+/// Where a map layer goes relative to the sprites
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LayerPass {
+    // ground, floors: always under everything
+    Below,
+    // each object (touching tiles) sorts against the sprites by its base
+    Sorted,
+    // birds, grass tips: always on top
+    Above,
+}
+
+/// Flat layers before the first sorted one go under the sprites and the
+/// rest go over them, so a flat layer stuck between two sorted ones covers
+/// the dog. Sort it too if that's not what you want.
+pub fn layer_passes<'a>(layers: impl Iterator<Item = &'a TmxTileLayer>, rules: &SortRules) -> Vec<LayerPass> {
+    let mut seen_sorted = false;
+    layers.map(|layer| {
+        if rules.sorts(layer) {
+            seen_sorted = true;
+            LayerPass::Sorted
+        } else if seen_sorted {
+            LayerPass::Above
+        } else {
+            LayerPass::Below
+        }
+    }).collect()
+}
+
+/// Depth of each tile on a sorted layer: the bottom of the lowest row of
+/// the object it belongs to, where an object is a group of tiles touching
+/// side by side or top to bottom. A tree three tiles tall then goes behind
+/// or in front of a dog as a whole instead of being sliced at every row.
+/// `bottom_lefts` are the tiles' corners, `step` the size of one cell.
+pub fn object_bases(bottom_lefts: &[Vector2], step: Vector2) -> Vec<f32> {
+    let Some(first) = bottom_lefts.first() else { return Vec::new() };
+    let cell_of = |at: &Vector2| (((at.x - first.x) / step.x).round() as i32, ((at.y - first.y) / step.y).round() as i32);
+    let cells: HashMap<(i32, i32), usize> = bottom_lefts.iter().enumerate().map(|(i, at)| (cell_of(at), i)).collect();
+
+    let mut bases = vec![f32::NAN; bottom_lefts.len()];
+    for start in 0..bottom_lefts.len() {
+        if !bases[start].is_nan() {
+            continue;
+        }
+
+        // flood the object, then give every tile in it the lowest bottom
+        let mut object = vec![start];
+        let mut next = 0;
+        bases[start] = 0.0;
+        while let Some(&i) = object.get(next) {
+            next += 1;
+            let (column, row) = cell_of(&bottom_lefts[i]);
+            for neighbour in [(column - 1, row), (column + 1, row), (column, row - 1), (column, row + 1)] {
+                if let Some(&j) = cells.get(&neighbour) && bases[j].is_nan() {
+                    bases[j] = 0.0;
+                    object.push(j);
+                }
+            }
+        }
+
+        let base = object.iter().map(|&i| bottom_lefts[i].y).fold(f32::MIN, f32::max);
+        for i in object {
+            bases[i] = base;
+        }
+    }
+    bases
+}
+
+enum Sortable<'s, 'a> {
+    Tile { gid: Gid, bottom_left: Vector2, tint: Color },
+    Sprite(&'s Sprite<'a>),
+}
+
+/// Draws the map with the sprites walking among it: flat ground layers,
+/// then the sorted layers' objects and the sprites back to front, then
+/// whatever stays on top. At equal depth tiles go first, so a dog standing
+/// right at the foot of the fence is in front of it.
+pub fn draw_layered(d: &mut DrawList, map: &TileMap, origin: Vector2, scale: f32, rules: &SortRules, sprites: &[Sprite]) {
+    let layers: Vec<&TmxTileLayer> = map.tmx.tile_layers().collect();
+    let passes = layer_passes(layers.iter().copied(), rules);
+
+    for (layer, pass) in layers.iter().zip(&passes) {
+        if *pass == LayerPass::Below {
+            map.draw_layer(d, layer, origin, scale);
+        }
+    }
+
+    // (depth, layer order, item); sprites come after every layer at the same depth
+    let mut items: Vec<(f32, usize, Sortable)> = Vec::new();
+    for (i, (layer, pass)) in layers.iter().zip(&passes).enumerate() {
+        if *pass != LayerPass::Sorted {
+            continue;
+        }
+        let tint = tilemap::layer_tint(layer);
+        let tiles: Vec<(Gid, Vector2)> = map.layer_tiles(layer, origin, scale).collect();
+        let bases = if layer.properties.get(BASE_PROPERTY).is_some_and(|base| base == "tile") {
+            tiles.iter().map(|(_, bottom_left)| bottom_left.y).collect()
+        } else {
+            let step = Vector2::new(map.tmx.tile_width as f32, map.tmx.tile_height as f32) * pixel_scale(scale);
+            object_bases(&tiles.iter().map(|(_, bottom_left)| *bottom_left).collect::<Vec<_>>(), step)
+        };
+        for ((gid, bottom_left), base) in tiles.into_iter().zip(bases) {
+            items.push((base, i, Sortable::Tile { gid, bottom_left, tint }));
+        }
+    }
+    for sprite in sprites {
+        items.push((sprite.depth(), layers.len(), Sortable::Sprite(sprite)));
+    }
+
+    items.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    let tile_scale = pixel_scale(scale);
+    for (_, _, item) in &items {
+        match item {
+            Sortable::Tile { gid, bottom_left, tint } => map.draw_tile(d, *gid, *bottom_left, tile_scale, *tint),
+            Sortable::Sprite(sprite) => sprite.draw(d),
+        }
+    }
+
+    for (layer, pass) in layers.iter().zip(&passes) {
+        if *pass == LayerPass::Above {
+            map.draw_layer(d, layer, origin, scale);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Vector2 = Vector2 { x: 16.0, y: 16.0 };
+
+    fn cell(column: i32, row: i32) -> Vector2 {
+        Vector2::new(column as f32 * 16.0, (row + 1) as f32 * 16.0)
+    }
+
+    #[test]
+    fn a_tall_tree_sorts_by_its_trunk() {
+        // crown two wide on rows 0 and 1, trunk on row 2
+        let tree = [cell(0, 0), cell(1, 0), cell(0, 1), cell(1, 1), cell(1, 2)];
+        assert_eq!(object_bases(&tree, STEP), vec![48.0; 5]);
+    }
+
+    #[test]
+    fn separate_objects_keep_their_own_base() {
+        // a bush at the top, a post further down and off to the side, and
+        // a rock touching the post only at a corner
+        let tiles = [cell(0, 0), cell(4, 2), cell(4, 3), cell(5, 4)];
+        assert_eq!(object_bases(&tiles, STEP), vec![16.0, 64.0, 64.0, 80.0]);
+    }
+
+    #[test]
+    fn a_dog_in_front_of_the_back_wall_is_drawn_over_it() {
+        use crate::animation::{AnimationDescriptor, AnimationPlayer};
+        use crate::draw::{self, DrawCommand};
+        use crate::tmx::TmxMap;
+        use std::sync::Arc;
+
+        let tmx = TmxMap::load("images/home/Interior1.tmx").unwrap();
+        let textures: Vec<_> = tmx.tilesets.iter().map(|_| draw::test_sheet(512, 512)).collect();
+        let map = TileMap::with_textures(tmx, textures.clone(), &[]);
+
+        // on the floor in column -5, two tiles down from the back wall that ends at row -3
+        let sheet = draw::test_sheet(16, 16);
+        let player = AnimationPlayer::new(AnimationDescriptor::new(sheet.clone(), vec![Rectangle::new(0.0, 0.0, 16.0, 16.0)], 0.1, true));
+        let sprites = [Sprite::new(&player, Vector2::new(-5.0 * 16.0 + 8.0, 8.0))];
+
+        let mut list = DrawList::new();
+        draw_layered(&mut list, &map, Vector2::new(0.0, 0.0), 1.0, &SortRules::new(&["Walls", "Windows"]), &sprites);
+
+        let dog_at = list.commands().iter()
+            .position(|(_, command)| matches!(command, DrawCommand::Texture { texture, .. } if Arc::ptr_eq(texture, &sheet)))
+            .unwrap();
+        let back_wall_at: Vec<usize> = list.commands().iter().enumerate().filter_map(|(i, (_, command))| match command {
+            // tiles are placed by their centre
+            DrawCommand::Texture { texture, dest, .. } if textures.iter().any(|sheet| Arc::ptr_eq(sheet, texture))
+                && dest.x == -5.0 * 16.0 + 8.0 && dest.y + 8.0 <= -32.0 => Some(i),
+            _ => None,
+        }).collect();
+
+        assert!(back_wall_at.len() >= 8, "only {} back wall tiles", back_wall_at.len());
+        assert!(back_wall_at.iter().all(|&i| i < dog_at), "the back wall covers the dog");
+    }
+
+    #[test]
+    fn bases_follow_the_scale_and_offset() {
+        let offset = Vector2::new(5.0, 3.0);
+        let tiles: Vec<Vector2> = [cell(2, 1), cell(2, 2), cell(7, 7)].iter().map(|at| *at * 2.0 + offset).collect();
+        assert_eq!(object_bases(&tiles, STEP * 2.0), vec![99.0, 99.0, 259.0]);
+        assert!(object_bases(&[], STEP).is_empty());
+    }
+}
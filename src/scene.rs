@@ -0,0 +1,290 @@
+use crate::assets::Assets;
+use crate::collision::{CollisionMap, Solidity};
+use crate::dog::{Dog, DogId, Surroundings};
+use crate::parallax::{self, Parallax};
+use crate::particles::ParticleSystem;
+use crate::render::Sprite;
+use crate::tilemap::TileMap;
+use crate::tmx::TmxMap;
+use raylib::prelude::{Rectangle, Vector2};
+use raylib::color::Color;
+use raylib::{RaylibHandle, RaylibThread};
+
+/// Object class that makes a TMX object a door
+pub const DOOR_CLASS: &str = "door";
+
+// each half of the fade between scenes, in seconds
+const FADE_SECONDS: f32 = 0.35;
+
+/// How a scene looks and feels apart from its map
+#[derive(Clone, Copy, Debug)]
+pub struct Ambient {
+    // forest_trees set drawn in parallax behind the map. None clears to `clear_color`
+    pub forest: Option<u32>,
+    pub clear_color: Color,
+    // under the sky, for the weather and time of day
+    pub outdoors: bool,
+}
+
+/// A scene as configured in code. Doors refer to scenes by `name`.
+#[derive(Clone, Copy, Debug)]
+pub struct SceneDef {
+    pub name: &'static str,
+    pub map: &'static str,
+    pub ambient: Ambient,
+    // most particles alive in the scene at once
+    pub particle_cap: usize,
+}
+
+/// A doorway out of a scene, from an object of class "door" in the map.
+/// Its "to" property names the scene on the other side and "spawn" the
+/// object there that you come out at.
+#[derive(Clone, Debug)]
+pub struct Door {
+    pub name: String,
+    pub area: Rectangle, // map pixels
+    pub to: String,
+    pub spawn: String,
+}
+
+/// One place the pet can be: the house, the yard and, later on, the park and the vet
+pub struct Scene {
+    pub name: &'static str,
+    pub ambient: Ambient,
+    // None when the map failed to load, the scene still works as an empty room
+    pub map: Option<TileMap>,
+    pub doors: Vec<Door>,
+    // animals that live here and carry on where they were when you come back
+    pub residents: Vec<Dog>,
+    // hearts, snores, dust... stay where they were while the scene isn't shown
+    pub particles: ParticleSystem,
+    background: Option<Parallax>,
+    no_walls: CollisionMap,
+}
+
+impl Scene {
+    pub fn load(def: &SceneDef, assets: &mut Assets, rl: &mut RaylibHandle, thread: &RaylibThread, solid_layers: &[(&str, Solidity)]) -> Self {
+        // a broken map shouldn't stop the game
+        let map = match TileMap::load(assets, rl, thread, def.map, solid_layers) {
+            Ok(map) => Some(map),
+            Err(err) => {
+                eprintln!("scene '{}': {:#}", def.name, err);
+                None
+            }
+        };
+
+        let doors = map.as_ref().map(|map| doors(&map.tmx)).unwrap_or_default();
+        let background = def.ambient.forest.and_then(|set| {
+            match Parallax::load(assets, rl, thread, &parallax::forest_dir(set)) {
+                Ok(background) => Some(background),
+                Err(err) => {
+                    eprintln!("scene '{}': {:#}", def.name, err);
+                    None
+                }
+            }
+        });
+
+        Self { map, doors, background, ..Self::empty(def) }
+    }
+
+    /// The scene with nothing in it, which is also what's left when its map won't load
+    pub fn empty(def: &SceneDef) -> Self {
+        Self {
+            name: def.name,
+            ambient: def.ambient,
+            map: None,
+            doors: Vec::new(),
+            residents: Vec::new(),
+            particles: ParticleSystem::new(def.particle_cap),
+            background: None,
+            no_walls: CollisionMap::empty(1.0, 1.0),
+        }
+    }
+
+    pub fn background(&self) -> Option<&Parallax> {
+        self.background.as_ref()
+    }
+
+    pub fn collision(&self) -> &CollisionMap {
+        self.map.as_ref().map_or(&self.no_walls, |map| &map.collision)
+    }
+
+    pub fn bounds(&self) -> Option<Rectangle> {
+        self.map.as_ref().map(|map| map.pixel_bounds())
+    }
+
+    /// Where to put something arriving at `spawn`, nudged out of any walls.
+    /// Falls back to the map's default spawn point when there's no such object.
+    pub fn arrival(&self, spawn: &str, body_at: impl Fn(Vector2) -> Rectangle) -> Option<Vector2> {
+        let map = self.map.as_ref()?;
+        let start = map.point(spawn).unwrap_or_else(|| map.spawn_point());
+        Some(map.collision.find_free(start, body_at).unwrap_or(start))
+    }
+
+    /// Makes `dog` live here, standing at `spawn` (see `arrival`)
+    pub fn move_in(&mut self, mut dog: Dog, spawn: &str) {
+        if let Some(position) = self.arrival(spawn, Dog::body_at) {
+            dog.position = position;
+        }
+        self.residents.push(dog);
+    }
+
+    /// The door `body` is standing in, if any
+    pub fn door_at(&self, body: Rectangle) -> Option<&Door> {
+        self.doors.iter().find(|door| body.check_collision_recs(&door.area))
+    }
+
+    /// Tile animations, residents and particles carry on only while the
+    /// scene is shown. `visitors` are pets here that don't live here, so
+    /// their particles can follow them.
+    pub fn update(&mut self, dt: f32, surroundings: &Surroundings, visitors: &[(DogId, Vector2)]) {
+        if let Some(map) = &mut self.map {
+            map.update(dt);
+        }
+
+        let collision = self.map.as_ref().map_or(&self.no_walls, |map| &map.collision);
+        for resident in &mut self.residents {
+            resident.apply_drains(surroundings);
+            let signals = resident.update(dt, collision);
+            self.particles.animation_events(&signals, resident.position, surroundings.wetness);
+            self.particles.follow_pet(resident);
+        }
+
+        let pets: Vec<(DogId, Vector2)> = self.residents.iter()
+            .map(|resident| (resident.id, resident.position))
+            .chain(visitors.iter().copied())
+            .collect();
+        self.particles.update(dt, &pets);
+    }
+
+    pub fn resident_sprites(&self) -> impl Iterator<Item = Sprite<'_>> {
+        self.residents.iter().filter_map(|resident| resident.sprite())
+    }
+}
+
+fn doors(tmx: &TmxMap) -> Vec<Door> {
+    let mut doors = Vec::new();
+
+    for group in tmx.object_groups() {
+        for object in group.objects.iter().filter(|object| object.class == DOOR_CLASS) {
+            let Some(to) = object.properties.get("to") else {
+                eprintln!("door '{}' doesn't say where it goes (needs a 'to' property)", object.name);
+                continue;
+            };
+            doors.push(Door {
+                name: object.name.clone(),
+                area: Rectangle::new(object.x + group.offset_x, object.y + group.offset_y, object.width, object.height),
+                to: to.clone(),
+                spawn: object.properties.get("spawn").cloned().unwrap_or_else(|| "spawn".to_string()),
+            });
+        }
+    }
+
+    doors
+}
+
+/// Where a scene change is up to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TransitionStep {
+    Fading,
+    // the screen is black: swap the scenes now
+    Switch,
+    Done,
+}
+
+/// Fade to black, change scene, fade back in
+pub struct SceneTransition {
+    pub to: usize,
+    pub spawn: String,
+    elapsed: f32,
+    switched: bool,
+}
+
+impl SceneTransition {
+    pub fn new(to: usize, spawn: String) -> Self {
+        Self { to, spawn, elapsed: 0.0, switched: false }
+    }
+
+    pub fn update(&mut self, dt: f32) -> TransitionStep {
+        self.elapsed += dt;
+
+        if !self.switched && self.elapsed >= FADE_SECONDS {
+            self.switched = true;
+            return TransitionStep::Switch;
+        }
+        if self.switched && self.elapsed >= FADE_SECONDS * 2.0 {
+            return TransitionStep::Done;
+        }
+        TransitionStep::Fading
+    }
+
+    /// How dark the screen is, 0 to 1
+    pub fn darkness(&self) -> f32 {
+        let progress = self.elapsed / FADE_SECONDS;
+        if progress <= 1.0 { progress } else { (2.0 - progress).max(0.0) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn a_transition_switches_once_at_the_darkest_point() {
+        let mut transition = SceneTransition::new(1, "spawn".to_string());
+        assert_eq!(transition.darkness(), 0.0);
+
+        let mut steps = Vec::new();
+        let mut darkest = 0.0_f32;
+        for _ in 0..120 {
+            steps.push(transition.update(DT));
+            darkest = darkest.max(transition.darkness());
+        }
+
+        let switch_at = steps.iter().position(|step| *step == TransitionStep::Switch).unwrap();
+        assert_eq!(steps.iter().filter(|step| **step == TransitionStep::Switch).count(), 1);
+        assert_eq!(switch_at, (FADE_SECONDS / DT).ceil() as usize - 1);
+        assert!((darkest - 1.0).abs() < 0.05);
+
+        let done_at = steps.iter().position(|step| *step == TransitionStep::Done).unwrap();
+        assert!(done_at > switch_at);
+        assert!(((done_at + 1) as f32 * DT - FADE_SECONDS * 2.0).abs() < DT * 1.5);
+        assert!(steps[..switch_at].iter().all(|step| *step == TransitionStep::Fading));
+        assert_eq!(transition.darkness(), 0.0);
+    }
+
+    #[test]
+    fn doors_come_from_door_objects() {
+        let xml = r#"<map orientation="orthogonal" tilewidth="16" tileheight="16" infinite="0">
+            <objectgroup name="Doors" offsetx="10" offsety="-4">
+                <object id="1" name="front_door" type="door" x="4" y="84" width="40" height="12">
+                    <properties>
+                        <property name="to" value="yard"/>
+                        <property name="spawn" value="front_door_outside"/>
+                    </properties>
+                </object>
+                <object id="2" name="back_door" class="door" x="100" y="20" width="16" height="8">
+                    <properties><property name="to" value="garden"/></properties>
+                </object>
+                <object id="3" name="nowhere" type="door" x="0" y="0" width="8" height="8"/>
+                <object id="4" name="rug" type="decor" x="0" y="0" width="8" height="8">
+                    <properties><property name="to" value="yard"/></properties>
+                </object>
+            </objectgroup>
+        </map>"#;
+        let tmx = TmxMap::parse(xml, Path::new("")).unwrap();
+
+        let doors = doors(&tmx);
+        assert_eq!(doors.len(), 2);
+
+        assert_eq!(doors[0].name, "front_door");
+        assert_eq!(doors[0].area, Rectangle::new(14.0, 80.0, 40.0, 12.0));
+        assert_eq!((doors[0].to.as_str(), doors[0].spawn.as_str()), ("yard", "front_door_outside"));
+
+        // no spawn property means the other side's default spawn
+        assert_eq!((doors[1].to.as_str(), doors[1].spawn.as_str()), ("garden", "spawn"));
+    }
+}
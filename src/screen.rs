@@ -0,0 +1,151 @@
+use raylib::prelude::{RaylibDraw, RaylibDrawHandle, RaylibTextureMode, RaylibTextureModeExt, RenderTexture2D, Rectangle, Vector2};
+use raylib::color::Color;
+use raylib::{RaylibHandle, RaylibThread};
+
+/// Height everything is drawn at before it's scaled up to the window
+pub const VIRTUAL_H: i32 = 360;
+// the width follows the window's shape between 4:3 and about 21:9.
+// anything wider or taller than that gets bars
+const MIN_W: i32 = 480;
+const MAX_W: i32 = 840;
+
+/// How the virtual screen sits in a window
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Fit {
+    pub width: i32,
+    pub height: i32,
+    // whole numbers, unless the window is smaller than the virtual screen
+    pub scale: f32,
+    // window pixels
+    pub dest: Rectangle,
+}
+
+/// The biggest whole-number scale that fits the window, with the virtual
+/// width stretched to use as much of the rest as it can
+pub fn fit(window_w: i32, window_h: i32) -> Fit {
+    let (window_w, window_h) = (window_w.max(1) as f32, window_h.max(1) as f32);
+
+    let most = f32::min(window_w / MIN_W as f32, window_h / VIRTUAL_H as f32);
+    let scale = if most >= 1.0 { most.floor() } else { most };
+    let width = ((window_w / scale).floor() as i32).clamp(MIN_W, MAX_W);
+
+    let (dest_w, dest_h) = ((width as f32 * scale).round(), (VIRTUAL_H as f32 * scale).round());
+    let dest = Rectangle::new(((window_w - dest_w) * 0.5).floor(), ((window_h - dest_h) * 0.5).floor(), dest_w, dest_h);
+
+    Fit { width, height: VIRTUAL_H, scale, dest }
+}
+
+/// The game draws into this at the virtual resolution, then it's scaled
+/// up to the window with bars around it
+pub struct Screen {
+    target: RenderTexture2D,
+    fit: Fit,
+}
+
+impl Screen {
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> anyhow::Result<Self> {
+        let fit = fit(rl.get_screen_width(), rl.get_screen_height());
+        let target = load_target(rl, thread, &fit)?;
+        Ok(Self { target, fit })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.fit.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.fit.height
+    }
+
+    /// Call once a frame. Returns true when the virtual size changed and
+    /// the layout needs redoing.
+    pub fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> anyhow::Result<bool> {
+        // checked every frame rather than on resize events, going fullscreen
+        // doesn't always send one
+        let fit = fit(rl.get_screen_width(), rl.get_screen_height());
+        let resized = (fit.width, fit.height) != (self.fit.width, self.fit.height);
+        if resized {
+            self.target = load_target(rl, thread, &fit)?;
+        }
+        self.fit = fit;
+        Ok(resized)
+    }
+
+    /// Borderless fullscreen on the current monitor, or back to a window
+    pub fn toggle_fullscreen(&self, rl: &mut RaylibHandle) {
+        rl.toggle_borderless_windowed();
+    }
+
+    /// Where a window point (the mouse) is on the virtual screen
+    pub fn to_virtual(&self, point: Vector2) -> Vector2 {
+        Vector2::new((point.x - self.fit.dest.x) / self.fit.scale, (point.y - self.fit.dest.y) / self.fit.scale)
+    }
+
+    /// Runs `draw` on the virtual screen, then puts that on the window
+    pub fn present(&mut self, d: &mut RaylibDrawHandle, thread: &RaylibThread, draw: impl FnOnce(&mut RaylibTextureMode<RaylibDrawHandle>)) {
+        {
+            let mut canvas = d.begin_texture_mode(thread, &mut self.target);
+            draw(&mut canvas);
+        }
+
+        d.clear_background(Color::BLACK);
+        // render textures come out upside down
+        let src = Rectangle::new(0.0, 0.0, self.fit.width as f32, -self.fit.height as f32);
+        d.draw_texture_pro(&self.target, src, self.fit.dest, Vector2::new(0.0, 0.0), 0.0, Color::WHITE);
+    }
+}
+
+fn load_target(rl: &mut RaylibHandle, thread: &RaylibThread, fit: &Fit) -> anyhow::Result<RenderTexture2D> {
+    rl.load_render_texture(thread, fit.width as u32, fit.height as u32)
+        .map_err(|err| anyhow::anyhow!("couldn't make a {}x{} screen: {}", fit.width, fit.height, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales_by_whole_numbers_and_widens_to_the_window() {
+        let exact = fit(1280, 720);
+        assert_eq!((exact.width, exact.height, exact.scale), (640, 360, 2.0));
+        assert_eq!(exact.dest, Rectangle::new(0.0, 0.0, 1280.0, 720.0));
+
+        // 1920x1080 is 3x, and 2560x1080 stays 3x with a wider screen
+        assert_eq!((fit(1920, 1080).width, fit(1920, 1080).scale), (640, 3.0));
+        assert_eq!((fit(2560, 1080).width, fit(2560, 1080).scale), (840, 3.0));
+
+        // a window just short of the next scale pads instead of blurring
+        let short = fit(1280, 1079);
+        assert_eq!((short.width, short.scale), (640, 2.0));
+        assert_eq!(short.dest, Rectangle::new(0.0, 179.0, 1280.0, 720.0));
+    }
+
+    #[test]
+    fn width_stays_between_4_3_and_21_9() {
+        // too tall: bars above and below
+        let tall = fit(960, 1080);
+        assert_eq!((tall.width, tall.scale), (480, 2.0));
+        assert_eq!(tall.dest, Rectangle::new(0.0, 180.0, 960.0, 720.0));
+
+        // too wide: bars at the sides
+        let wide = fit(3440, 720);
+        assert_eq!((wide.width, wide.scale), (840, 2.0));
+        assert_eq!(wide.dest, Rectangle::new(880.0, 0.0, 1680.0, 720.0));
+
+        for (w, h) in [(1, 1), (800, 600), (1366, 768), (5120, 1440)] {
+            let fit = fit(w, h);
+            assert!((MIN_W..=MAX_W).contains(&fit.width), "{}x{} gave {}", w, h, fit.width);
+            assert_eq!(fit.height, VIRTUAL_H);
+        }
+    }
+
+    #[test]
+    fn small_windows_shrink_to_fit() {
+        let small = fit(240, 180);
+        assert_eq!((small.width, small.scale), (480, 0.5));
+        assert_eq!(small.dest, Rectangle::new(0.0, 0.0, 240.0, 180.0));
+
+        // a minimised window is 0x0
+        assert!(fit(0, 0).scale > 0.0);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::animation::AnimationBank;
+use crate::assets::Assets;
+use crate::breeding::Lineage;
+use crate::dog::{Dog, DogBreed};
+use crate::genetics::{Genome, Personality};
+use crate::rng::Rng;
+use crate::types::Gender;
+use raylib::{RaylibHandle, RaylibThread};
+use std::sync::Arc;
+
+pub const NAMES: [&str; 40] = [
+    "Scottie", "Biscuit", "Pepper", "Luna", "Milo", "Daisy", "Rocky", "Bella",
+    "Ziggy", "Maple", "Otis", "Rosie", "Bruno", "Hazel", "Tucker", "Penny",
+    "Gus", "Willow", "Murphy", "Olive", "Bear", "Cookie", "Duke", "Nala",
+    "Waffles", "Ginger", "Scout", "Pickles", "Moose", "Juniper", "Benny", "Clover",
+    "Toby", "Poppy", "Rex", "Mochi", "Chester", "Pearl", "Bandit", "Sunny",
+];
+
+const ORIGINS: [&str; 8] = [
+    "was found wandering near the old train station",
+    "was surrendered when their family moved overseas",
+    "came in with a litter rescued from a farm",
+    "was picked up by animal control after a thunderstorm",
+    "was returned after their owner's allergies got worse",
+    "turned up on the shelter's doorstep one morning",
+    "was rescued from a backyard breeder",
+    "lost their home when their elderly owner passed away",
+];
+
+// picked based on personality, see `backstory`
+const HIGH_ENERGY: &str = "Never stops moving and needs long walks.";
+const LOW_ENERGY: &str = "Happiest napping in a sunny spot.";
+const SOCIAL: &str = "Greets every volunteer with a wagging tail.";
+const SHY: &str = "Takes a while to warm up to new people.";
+const SMART: &str = "Already knows sit, stay and shake.";
+const BOLD: &str = "Not scared of anything, including the vacuum.";
+
+/// A dog waiting to be adopted. Becomes a real `Dog` on adoption.
+pub struct ShelterCandidate {
+    pub name: String,
+    pub breed: DogBreed,
+    pub gender: Gender,
+    pub date_of_birth: chrono::NaiveDate,
+    pub genome: Genome,
+    pub backstory: String,
+}
+
+impl ShelterCandidate {
+    pub fn generate(rng: &mut Rng, today: chrono::NaiveDate) -> Self {
+        let breed = *rng.pick(&DogBreed::ALL);
+        let gender = if rng.chance(0.5) { Gender::Boy } else { Gender::Girl };
+
+        // anywhere from a 3 month old puppy to a 12 year old senior
+        let age_days = rng.range(90, 12 * 365);
+        let date_of_birth = today - chrono::TimeDelta::days(age_days);
+
+        // same breed standard as everyone else, but nobody is exactly average
+        let mut genome = Genome::for_breed(breed);
+        genome.personality = Personality::inherit(&genome.personality, &genome.personality, rng);
+
+        let name = rng.pick(&NAMES).to_string();
+        let backstory = backstory(rng, &name, &genome.personality);
+
+        Self { name, breed, gender, date_of_birth, genome, backstory }
+    }
+
+    pub fn age_years(&self, today: chrono::NaiveDate) -> f32 {
+        (today - self.date_of_birth).num_days() as f32 / 365.0
+    }
+
+    pub fn into_dog(self, assets: &mut Assets, rl: &mut RaylibHandle, thread: &RaylibThread) -> Dog {
+        let animations = Dog::load_animations(assets, rl, thread);
+        self.with_animations(animations)
+    }
+
+    /// Same as `into_dog`, drawn with sprites that are already loaded
+    pub fn with_animations(self, animations: Arc<AnimationBank>) -> Dog {
+        Dog::with_animations(self.name, self.breed, self.gender, self.date_of_birth, self.genome, Lineage::founder(), animations)
+    }
+}
+
+fn backstory(rng: &mut Rng, name: &str, personality: &Personality) -> String {
+    let mut story = format!("{} {}.", name, rng.pick(&ORIGINS));
+
+    let traits = [
+        (personality.energy >= 75, HIGH_ENERGY),
+        (personality.energy <= 40, LOW_ENERGY),
+        (personality.sociability >= 80, SOCIAL),
+        (personality.sociability <= 45, SHY),
+        (personality.trainability >= 85, SMART),
+        (personality.boldness >= 75, BOLD),
+    ];
+
+    for (_, line) in traits.iter().filter(|(applies, _)| *applies).take(2) {
+        story.push(' ');
+        story.push_str(line);
+    }
+
+    story
+}
+
+/// The dogs currently up for adoption, plus which one the player is looking at
+pub struct Shelter {
+    pub candidates: Vec<ShelterCandidate>,
+    pub selected: usize,
+}
+
+impl Shelter {
+    /// Same seed and date always fill the shelter with the same dogs
+    pub fn generate(seed: u64, count: usize, today: chrono::NaiveDate) -> Self {
+        let mut rng = Rng::new(seed);
+        let candidates = (0..count).map(|_| ShelterCandidate::generate(&mut rng, today)).collect();
+
+        Self { candidates, selected: 0 }
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.candidates.is_empty() {
+            self.selected = (self.selected + 1) % self.candidates.len();
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        if !self.candidates.is_empty() {
+            self.selected = (self.selected + self.candidates.len() - 1) % self.candidates.len();
+        }
+    }
+
+    pub fn current(&self) -> Option<&ShelterCandidate> {
+        self.candidates.get(self.selected)
+    }
+
+    /// Takes the selected dog out of the shelter
+    pub fn adopt(&mut self) -> Option<ShelterCandidate> {
+        if self.selected >= self.candidates.len() {
+            return None;
+        }
+
+        let candidate = self.candidates.remove(self.selected);
+        self.selected = self.selected.min(self.candidates.len().saturating_sub(1));
+        Some(candidate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, 14).unwrap()
+    }
+
+    fn summary(shelter: &Shelter) -> Vec<(String, DogBreed, Gender, NaiveDate, String)> {
+        shelter.candidates.iter()
+            .map(|c| (c.name.clone(), c.breed, c.gender, c.date_of_birth, c.backstory.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_and_date_fill_the_same_shelter() {
+        let (a, b) = (Shelter::generate(42, 6, today()), Shelter::generate(42, 6, today()));
+        assert_eq!(summary(&a), summary(&b));
+        assert!(a.candidates.iter().zip(&b.candidates).all(|(x, y)| x.genome == y.genome));
+
+        assert_ne!(summary(&a), summary(&Shelter::generate(43, 6, today())));
+        assert_eq!(a.candidates.len(), 6);
+    }
+
+    #[test]
+    fn ages_run_from_puppy_to_senior() {
+        let shelter = Shelter::generate(7, 200, today());
+        for candidate in &shelter.candidates {
+            let age = candidate.age_years(today());
+            assert!((90.0 / 365.0..12.0).contains(&age), "{} is {} years old", candidate.name, age);
+            assert!(candidate.backstory.starts_with(&candidate.name));
+        }
+    }
+
+    #[test]
+    fn browsing_wraps_around() {
+        let mut shelter = Shelter::generate(1, 3, today());
+        shelter.select_prev();
+        assert_eq!(shelter.selected, 2);
+        shelter.select_next();
+        assert_eq!(shelter.selected, 0);
+        shelter.select_next();
+        assert_eq!(shelter.selected, 1);
+
+        // and does nothing in an empty shelter
+        let mut empty = Shelter::generate(1, 0, today());
+        empty.select_next();
+        empty.select_prev();
+        assert_eq!(empty.selected, 0);
+        assert!(empty.current().is_none());
+    }
+
+    #[test]
+    fn adopting_the_last_dog_in_the_list_moves_the_selection_back() {
+        let mut shelter = Shelter::generate(1, 3, today());
+        let names: Vec<String> = shelter.candidates.iter().map(|c| c.name.clone()).collect();
+
+        shelter.selected = 2;
+        assert_eq!(shelter.adopt().unwrap().name, names[2]);
+        assert_eq!(shelter.selected, 1);
+        assert_eq!(shelter.current().unwrap().name, names[1]);
+
+        shelter.selected = 0;
+        assert_eq!(shelter.adopt().unwrap().name, names[0]);
+        assert_eq!(shelter.adopt().unwrap().name, names[1]);
+        assert_eq!(shelter.selected, 0);
+        assert!(shelter.adopt().is_none());
+    }
+}
@@ -0,0 +1,262 @@
+use crate::animation::Pose;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+
+/// When an edge of the pose graph may be taken
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    Always,
+    Moving,
+    Still,
+}
+
+/// What the owner is doing this tick, checked against `Condition`s
+#[derive(Clone, Copy, Debug)]
+pub struct Conditions {
+    pub moving: bool,
+}
+
+impl Condition {
+    pub fn holds(&self, conditions: &Conditions) -> bool {
+        match self {
+            Condition::Always => true,
+            Condition::Moving => conditions.moving,
+            Condition::Still => !conditions.moving,
+        }
+    }
+}
+
+/// One edge of the pose graph, e.g. standing -> sleeping by way of "lie_down".
+/// Declared in the sprite manifest's "transitions" list.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Transition {
+    pub from: Pose,
+    pub to: Pose,
+    // played once between the two poses' loops. None switches straight over
+    #[serde(default)]
+    pub clip: Option<String>,
+    // play the clip backwards, so lying down doubles as getting up
+    #[serde(default)]
+    pub reversed: bool,
+    #[serde(default = "default_condition")]
+    pub when: Condition,
+}
+
+fn default_condition() -> Condition { Condition::Always }
+
+/// Every declared transition between poses. Poses without a path between
+/// them just snap, like they did before there was a graph.
+#[derive(Clone, Debug, Default)]
+pub struct StateGraph {
+    pub transitions: Vec<Transition>,
+}
+
+impl StateGraph {
+    /// First edge on the shortest path from `from` to `to` using only edges
+    /// whose condition holds right now
+    pub fn next_edge(&self, from: Pose, to: Pose, conditions: &Conditions) -> Option<&Transition> {
+        self.search(from, to, |edge| edge.when.holds(conditions))
+    }
+
+    /// Whether the graph has any route between the poses, whatever the conditions
+    pub fn connected(&self, from: Pose, to: Pose) -> bool {
+        self.search(from, to, |_| true).is_some()
+    }
+
+    fn search(&self, from: Pose, to: Pose, usable: impl Fn(&Transition) -> bool) -> Option<&Transition> {
+        if from == to {
+            return None;
+        }
+
+        // breadth first, remembering the edge we left the start pose by
+        let mut first_edge: HashMap<Pose, usize> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(pose) = queue.pop_front() {
+            for (i, edge) in self.transitions.iter().enumerate() {
+                if edge.from != pose || !usable(edge) {
+                    continue;
+                }
+                if edge.to == from || first_edge.contains_key(&edge.to) {
+                    continue;
+                }
+
+                let first = if pose == from { i } else { first_edge[&pose] };
+                if edge.to == to {
+                    return self.transitions.get(first);
+                }
+                first_edge.insert(edge.to, first);
+                queue.push_back(edge.to);
+            }
+        }
+
+        None
+    }
+}
+
+/// What the owner should show next
+#[derive(Clone, Debug, PartialEq)]
+pub enum GraphStep {
+    // play this clip once, then call `finish_transition`
+    Transition { clip: String, reversed: bool },
+    // switch to the pose's loop now
+    Enter(Pose),
+}
+
+/// Runtime side of the graph: which transition clip, if any, is playing
+#[derive(Default)]
+pub struct AnimationStateMachine {
+    pending: Option<Pose>,
+}
+
+impl AnimationStateMachine {
+    pub fn new() -> Self {
+        Self { pending: None }
+    }
+
+    pub fn in_transition(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Works out the next move from `current` towards `target`. Does nothing
+    /// while a transition clip is still playing.
+    pub fn step(&mut self, graph: &StateGraph, current: Pose, target: Pose, conditions: &Conditions) -> Option<GraphStep> {
+        if self.pending.is_some() || current == target {
+            return None;
+        }
+
+        let Some(edge) = graph.next_edge(current, target, conditions) else {
+            // a declared route that just isn't open yet is waited for
+            if graph.connected(current, target) {
+                return None;
+            }
+            return Some(GraphStep::Enter(target));
+        };
+
+        match &edge.clip {
+            Some(clip) => {
+                self.pending = Some(edge.to);
+                Some(GraphStep::Transition { clip: clip.clone(), reversed: edge.reversed })
+            },
+            None => Some(GraphStep::Enter(edge.to)),
+        }
+    }
+
+    /// The transition clip is done. Returns the pose to enter.
+    pub fn finish_transition(&mut self) -> Option<Pose> {
+        self.pending.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::tests::clip;
+    use crate::animator::{AnimationSignal, Animator, OneShotPriority};
+
+    const STILL: Conditions = Conditions { moving: false };
+    const MOVING: Conditions = Conditions { moving: true };
+
+    fn edge(from: Pose, to: Pose, clip: Option<&str>, reversed: bool, when: Condition) -> Transition {
+        Transition { from, to, clip: clip.map(str::to_string), reversed, when }
+    }
+
+    // the same shape as the pixeldoggies manifest
+    fn dog_graph() -> StateGraph {
+        StateGraph {
+            transitions: vec![
+                edge(Pose::Standing, Pose::Walking, None, false, Condition::Moving),
+                edge(Pose::Walking, Pose::Standing, None, false, Condition::Still),
+                edge(Pose::Standing, Pose::Sleeping, Some("lie_down"), false, Condition::Always),
+                edge(Pose::Sleeping, Pose::Standing, Some("lie_down"), true, Condition::Always),
+                edge(Pose::Standing, Pose::Sitting, Some("sit"), false, Condition::Always),
+                edge(Pose::Sitting, Pose::Standing, Some("sit"), true, Condition::Always),
+            ],
+        }
+    }
+
+    #[test]
+    fn next_edge_takes_the_first_step_of_the_shortest_route() {
+        let graph = dog_graph();
+
+        let first = graph.next_edge(Pose::Sitting, Pose::Sleeping, &STILL).unwrap();
+        assert_eq!((first.from, first.to, first.reversed), (Pose::Sitting, Pose::Standing, true));
+
+        let first = graph.next_edge(Pose::Walking, Pose::Sitting, &STILL).unwrap();
+        assert_eq!((first.from, first.to), (Pose::Walking, Pose::Standing));
+
+        assert!(graph.next_edge(Pose::Standing, Pose::Standing, &STILL).is_none());
+    }
+
+    #[test]
+    fn closed_edges_are_waited_for_and_missing_ones_snap() {
+        let graph = dog_graph();
+        let mut machine = AnimationStateMachine::new();
+
+        // still walking: the way to standing only opens once the dog stops
+        assert_eq!(machine.step(&graph, Pose::Walking, Pose::Sitting, &MOVING), None);
+        assert_eq!(machine.step(&graph, Pose::Walking, Pose::Sitting, &STILL), Some(GraphStep::Enter(Pose::Standing)));
+
+        // nothing leads to hind legs, so it's switched to straight away
+        assert_eq!(machine.step(&graph, Pose::Standing, Pose::HindLegs, &STILL), Some(GraphStep::Enter(Pose::HindLegs)));
+        assert!(!machine.in_transition());
+    }
+
+    #[test]
+    fn a_transition_clip_is_played_before_the_pose_is_entered() {
+        let graph = dog_graph();
+        let mut machine = AnimationStateMachine::new();
+
+        let step = machine.step(&graph, Pose::Standing, Pose::Sitting, &STILL);
+        assert_eq!(step, Some(GraphStep::Transition { clip: "sit".to_string(), reversed: false }));
+        assert!(machine.in_transition());
+
+        // nothing else happens until the clip is done
+        assert_eq!(machine.step(&graph, Pose::Standing, Pose::Sleeping, &STILL), None);
+        assert_eq!(machine.finish_transition(), Some(Pose::Sitting));
+        assert_eq!(machine.finish_transition(), None);
+
+        let step = machine.step(&graph, Pose::Sitting, Pose::Standing, &STILL);
+        assert_eq!(step, Some(GraphStep::Transition { clip: "sit".to_string(), reversed: true }));
+    }
+
+    #[test]
+    fn the_animator_reports_the_end_of_a_transition_once() {
+        let mut animator = Animator::new(clip(4, 0.1, true), false);
+
+        // getting up is sitting down backwards, from the last frame
+        animator.play_transition(clip(3, 0.1, true), true, false);
+        assert_eq!(animator.current().current_frame, 2);
+
+        let signals: Vec<AnimationSignal> = (0..20).flat_map(|_| animator.update(0.05)).collect();
+        let finished = signals.iter().filter(|signal| **signal == AnimationSignal::TransitionFinished).count();
+        assert_eq!(finished, 1);
+        // the last frame is held rather than looped
+        assert_eq!(animator.current().current_frame, 0);
+        assert!(!animator.current().playing);
+    }
+
+    #[test]
+    fn a_one_shot_over_a_held_pose_goes_back_to_it() {
+        let sitting_down = clip(3, 0.1, false);
+        let mut sat = clip(1, 0.1, true);
+        sat.frames[0].x = 32.0;
+
+        let mut animator = Animator::new(clip(4, 0.1, true), false);
+        animator.play_transition(sitting_down, false, false);
+        for _ in 0..10 {
+            animator.update(0.05);
+        }
+        animator.set_base(sat.clone(), false);
+
+        animator.play_once("bark", clip(2, 0.1, false), OneShotPriority::Normal);
+        let signals: Vec<AnimationSignal> = (0..10).flat_map(|_| animator.update(0.05)).collect();
+        assert!(signals.contains(&AnimationSignal::Finished("bark".to_string())));
+
+        // back on the held frame, not sitting down again
+        assert!(animator.current().descriptor.same_clip(&sat));
+        assert_eq!(animator.current().current_frame_rect().x, 32.0);
+    }
+}
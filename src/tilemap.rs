@@ -0,0 +1,238 @@
+use crate::animation::{AnimationDescriptor, AnimationPlayer, Sheet};
+use crate::draw::DrawList;
+use crate::assets::Assets;
+use crate::collision::{CollisionMap, Solidity};
+use crate::render::pixel_scale;
+use crate::tmx::{Gid, TmxMap, TmxTileLayer};
+use raylib::prelude::{Rectangle, Vector2};
+use raylib::color::Color;
+use raylib::{RaylibHandle, RaylibThread};
+use std::collections::HashMap;
+use anyhow::Context;
+
+/// A Tiled map with its tileset images loaded, ready to draw
+pub struct TileMap {
+    pub tmx: TmxMap,
+    pub collision: CollisionMap,
+    // same order as tmx.tilesets
+    textures: Vec<Sheet>,
+    // one player per animated tile id (without flip flags), shared by every
+    // cell showing that tile, so a yard full of trees costs one timer per tree tile
+    animations: HashMap<u32, AnimationPlayer>,
+}
+
+impl TileMap {
+    /// `solid_layers` name the layers the dog can't walk through, see `CollisionMap::from_tmx`
+    pub fn load(assets: &mut Assets, rl: &mut RaylibHandle, thread: &RaylibThread, path: &str, solid_layers: &[(&str, Solidity)]) -> anyhow::Result<Self> {
+        let tmx = TmxMap::load(path)?;
+
+        let mut textures = Vec::with_capacity(tmx.tilesets.len());
+        for tileset in &tmx.tilesets {
+            let texture = assets.texture(rl, thread, &tileset.image)
+                .with_context(|| format!("tileset '{}' of {}", tileset.name, path))?;
+            textures.push(texture);
+        }
+
+        Ok(Self::with_textures(tmx, textures, solid_layers))
+    }
+
+    /// A map whose tileset images are already loaded, one per tileset in order
+    pub fn with_textures(tmx: TmxMap, textures: Vec<Sheet>, solid_layers: &[(&str, Solidity)]) -> Self {
+        let collision = CollisionMap::from_tmx(&tmx, solid_layers);
+
+        let mut animations = HashMap::new();
+        for (tileset, texture) in tmx.tilesets.iter().zip(&textures) {
+            for (local_id, frames) in &tileset.animations {
+                if frames.is_empty() {
+                    continue;
+                }
+
+                let rects = frames.iter().map(|frame| {
+                    let (x, y) = tileset.tile_origin(frame.tile_id);
+                    Rectangle::new(x as f32, y as f32, tileset.tile_width as f32, tileset.tile_height as f32)
+                }).collect();
+                let durations = frames.iter().map(|frame| frame.duration_ms as f32 / 1000.0).collect();
+
+                let descriptor = AnimationDescriptor::new(texture.clone(), rects, 0.0, true).with_frame_durations(durations);
+                animations.insert(tileset.first_gid + local_id, AnimationPlayer::new(descriptor));
+            }
+        }
+
+        Self { tmx, collision, textures, animations }
+    }
+
+    /// Advance the tile animations by dt seconds of game time
+    pub fn update(&mut self, dt: f32) {
+        for player in self.animations.values_mut() {
+            player.update(dt);
+        }
+    }
+
+    /// A named object's position in map pixels, e.g. a point marking where
+    /// you come in through a door
+    pub fn point(&self, name: &str) -> Option<Vector2> {
+        self.tmx.object_groups()
+            .flat_map(|group| group.objects.iter().map(move |object| (group, object)))
+            .find(|(_, object)| object.name == name)
+            .map(|(group, object)| Vector2::new(object.x + group.offset_x, object.y + group.offset_y))
+    }
+
+    /// Where things arrive on this map by default: the object named "spawn"
+    /// if there is one, otherwise the middle of the painted area
+    pub fn spawn_point(&self) -> Vector2 {
+        self.point("spawn").unwrap_or_else(|| {
+            let bounds = self.pixel_bounds();
+            Vector2::new(bounds.x + bounds.width * 0.5, bounds.y + bounds.height * 0.5)
+        })
+    }
+
+    /// Size of the painted part of the map in pixels, at scale 1
+    pub fn pixel_bounds(&self) -> Rectangle {
+        match self.tmx.used_bounds() {
+            Some((x, y, w, h)) => Rectangle::new(
+                (x * self.tmx.tile_width) as f32,
+                (y * self.tmx.tile_height) as f32,
+                (w * self.tmx.tile_width) as f32,
+                (h * self.tmx.tile_height) as f32,
+            ),
+            None => Rectangle::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn draw_layer(&self, d: &mut DrawList, layer: &TmxTileLayer, origin: Vector2, scale: f32) {
+        let tint = layer_tint(layer);
+        for (gid, bottom_left) in self.layer_tiles(layer, origin, scale) {
+            self.draw_tile(d, gid, bottom_left, pixel_scale(scale), tint);
+        }
+    }
+
+    /// Every painted tile of a visible layer with the screen position of its
+    /// bottom-left corner. The bottom edge is where the tile meets the ground,
+    /// which is what it y-sorts by.
+    pub fn layer_tiles<'a>(&'a self, layer: &'a TmxTileLayer, origin: Vector2, scale: f32) -> impl Iterator<Item = (Gid, Vector2)> + 'a {
+        let shown = layer.visible && layer.opacity > 0.0;
+        let scale = pixel_scale(scale);
+        let cell_w = self.tmx.tile_width as f32;
+        let cell_h = self.tmx.tile_height as f32;
+
+        layer.chunks.iter().filter(move |_| shown).flat_map(move |chunk| {
+            chunk.tiles.iter().enumerate().filter(|(_, gid)| !gid.is_empty()).map(move |(i, gid)| {
+                let column = chunk.x + i as i32 % chunk.width;
+                let row = chunk.y + i as i32 / chunk.width;
+
+                // tiles taller than the grid hang up from the bottom of their cell, like in Tiled
+                let cell = Vector2::new(
+                    origin.x + (column as f32 * cell_w + layer.offset_x) * scale,
+                    origin.y + ((row + 1) as f32 * cell_h + layer.offset_y) * scale,
+                );
+                (*gid, cell)
+            })
+        })
+    }
+
+    /// Draws one tile with its bottom-left corner at `bottom_left`, applying the gid's flip flags
+    pub fn draw_tile(&self, d: &mut DrawList, gid: Gid, bottom_left: Vector2, scale: f32, tint: Color) {
+        let Some((index, tileset)) = self.tmx.tileset_for(gid) else { return };
+        let local_id = gid.id() - tileset.first_gid;
+        let (sx, sy) = tileset.tile_origin(local_id);
+
+        let w = tileset.tile_width as f32;
+        let h = tileset.tile_height as f32;
+        let mut src = match self.animations.get(&gid.id()) {
+            Some(player) => player.current_frame_rect(),
+            None => Rectangle::new(sx as f32, sy as f32, w, h),
+        };
+
+        // a diagonal flip is a 90 degree turn plus a vertical flip, so turn
+        // the tile and swap which axis the other two flags apply to
+        let (flip_x, flip_y, rotation) = if gid.flipped_diagonally() {
+            (gid.flipped_vertically(), !gid.flipped_horizontally(), 90.0)
+        } else {
+            (gid.flipped_horizontally(), gid.flipped_vertically(), 0.0)
+        };
+        if flip_x {
+            src.width = -src.width;
+        }
+        if flip_y {
+            src.height = -src.height;
+        }
+
+        // rotate around the tile's centre so it stays in its cell
+        let half = Vector2::new(w * scale / 2.0, h * scale / 2.0);
+        let dest = Rectangle::new(
+            bottom_left.x.round() + half.x,
+            bottom_left.y.round() - h * scale + half.y,
+            w * scale,
+            h * scale,
+        );
+
+        d.draw_texture_pro(&self.textures[index], src, dest, half, rotation, tint);
+    }
+}
+
+/// Tint a layer's tiles are drawn with
+pub fn layer_tint(layer: &TmxTileLayer) -> Color {
+    Color::WHITE.fade(layer.opacity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draw::{self, DrawCommand};
+    use std::path::Path;
+
+    // tile 0 shows tile 1 for 600ms then tile 2 for 150ms, tile 5 flicks
+    // between 6 and 7. the layer has tile 0 three times, once flipped, and a
+    // still tile 3 on the end
+    const MAP: &str = r#"<map orientation="orthogonal" tilewidth="16" tileheight="16" infinite="0">
+        <tileset firstgid="1" name="water" tilewidth="16" tileheight="16" tilecount="16" columns="4">
+            <image source="water.png" width="64" height="64"/>
+            <tile id="0"><animation><frame tileid="1" duration="600"/><frame tileid="2" duration="150"/></animation></tile>
+            <tile id="5"><animation><frame tileid="6" duration="100"/><frame tileid="7" duration="100"/></animation></tile>
+        </tileset>
+        <layer id="1" name="Water" width="5" height="1"><data encoding="csv">1,1,2147483649,6,4</data></layer>
+    </map>"#;
+
+    fn tile_map() -> TileMap {
+        let tmx = TmxMap::parse(MAP, Path::new("maps")).unwrap();
+        TileMap::with_textures(tmx, vec![draw::test_sheet(64, 64)], &[])
+    }
+
+    // where in the tileset each cell is drawn from, left to right
+    fn sources(map: &TileMap) -> Vec<(f32, f32)> {
+        let mut list = DrawList::new();
+        let layer = map.tmx.tile_layers().next().unwrap();
+        map.draw_layer(&mut list, layer, Vector2::new(0.0, 0.0), 1.0);
+        list.commands().iter().map(|(_, command)| match command {
+            DrawCommand::Texture { source, .. } => (source.x, source.y),
+            other => panic!("drew {:?}", other),
+        }).collect()
+    }
+
+    #[test]
+    fn every_cell_with_an_animated_tile_shares_its_player() {
+        let mut map = tile_map();
+        let mut ids: Vec<u32> = map.animations.keys().copied().collect();
+        ids.sort();
+        assert_eq!(ids, [1, 6]);
+
+        assert_eq!(sources(&map), [(16.0, 0.0), (16.0, 0.0), (16.0, 0.0), (32.0, 16.0), (48.0, 0.0)]);
+        map.update(0.72);
+        assert_eq!(sources(&map), [(32.0, 0.0), (32.0, 0.0), (32.0, 0.0), (48.0, 16.0), (48.0, 0.0)]);
+    }
+
+    #[test]
+    fn each_frame_lasts_as_long_as_the_map_says() {
+        let mut map = tile_map();
+        let water = |map: &TileMap| sources(map)[0].0;
+
+        map.update(0.59);
+        assert_eq!(water(&map), 16.0);
+        map.update(0.02);
+        assert_eq!(water(&map), 32.0);
+        map.update(0.14);
+        assert_eq!(water(&map), 32.0);
+        map.update(0.02);
+        assert_eq!(water(&map), 16.0);
+    }
+}
@@ -0,0 +1,620 @@
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{anyhow, bail, Context};
+use roxmltree::Node;
+
+// high bits of a gid say how the tile is flipped, see
+// https://doc.mapeditor.org/en/stable/reference/global-tile-ids/
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const ROTATED_HEXAGONAL_120: u32 = 0x1000_0000;
+const FLAG_MASK: u32 = FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL_120;
+
+/// Global tile id as stored in the map: tileset firstgid + local id, plus flip flags
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Gid(pub u32);
+
+impl Gid {
+    /// The tile without its flip flags. 0 means an empty cell.
+    pub fn id(&self) -> u32 { self.0 & !FLAG_MASK }
+    pub fn is_empty(&self) -> bool { self.id() == 0 }
+    pub fn flipped_horizontally(&self) -> bool { self.0 & FLIPPED_HORIZONTALLY != 0 }
+    pub fn flipped_vertically(&self) -> bool { self.0 & FLIPPED_VERTICALLY != 0 }
+    pub fn flipped_diagonally(&self) -> bool { self.0 & FLIPPED_DIAGONALLY != 0 }
+}
+
+/// One frame of a tile animation
+#[derive(Clone, Copy, Debug)]
+pub struct TmxFrame {
+    pub tile_id: u32, // local to the tileset
+    pub duration_ms: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct TmxTileset {
+    pub first_gid: u32,
+    pub name: String,
+    pub tile_width: i32,
+    pub tile_height: i32,
+    pub tile_count: u32,
+    pub columns: i32,
+    pub spacing: i32,
+    pub margin: i32,
+    pub image: String, // relative to the working directory
+    pub image_width: i32,
+    pub image_height: i32,
+    // keyed by local tile id
+    pub animations: HashMap<u32, Vec<TmxFrame>>,
+    pub tile_properties: HashMap<u32, HashMap<String, String>>,
+    // drawn in Tiled's collision editor, in pixels from the tile's top-left
+    pub tile_shapes: HashMap<u32, Vec<TmxObject>>,
+}
+
+impl TmxTileset {
+    pub fn contains(&self, gid: Gid) -> bool {
+        let id = gid.id();
+        id >= self.first_gid && id < self.first_gid + self.tile_count
+    }
+
+    /// Where a local tile id sits on the tileset image, in pixels
+    pub fn tile_origin(&self, local_id: u32) -> (i32, i32) {
+        let columns = self.columns.max(1) as u32;
+        let column = (local_id % columns) as i32;
+        let row = (local_id / columns) as i32;
+        (
+            self.margin + column * (self.tile_width + self.spacing),
+            self.margin + row * (self.tile_height + self.spacing),
+        )
+    }
+}
+
+/// A block of cells. Finite maps get a single chunk covering the whole map.
+#[derive(Clone, Debug)]
+pub struct TmxChunk {
+    pub x: i32, // in tiles
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub tiles: Vec<Gid>, // row by row
+}
+
+#[derive(Clone, Debug)]
+pub struct TmxTileLayer {
+    pub id: u32,
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub offset_x: f32, // pixels
+    pub offset_y: f32,
+    pub chunks: Vec<TmxChunk>,
+    pub properties: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TmxObject {
+    pub id: u32,
+    pub name: String,
+    pub class: String, // "type" before Tiled 1.9
+    pub x: f32, // pixels
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub gid: Option<Gid>, // tile objects
+    // a polygon's corners relative to (x, y). empty for every other shape
+    pub polygon: Vec<(f32, f32)>,
+    pub visible: bool,
+    pub properties: HashMap<String, String>,
+}
+
+impl TmxObject {
+    /// (x, y, width, height) in pixels. Polygons have no size of their own,
+    /// so theirs is the box around their corners.
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        if self.polygon.is_empty() {
+            return (self.x, self.y, self.width, self.height);
+        }
+
+        let (mut x0, mut y0, mut x1, mut y1) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for &(x, y) in &self.polygon {
+            (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+        }
+        (self.x + x0, self.y + y0, x1 - x0, y1 - y0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TmxObjectGroup {
+    pub id: u32,
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub objects: Vec<TmxObject>,
+    pub properties: HashMap<String, String>,
+}
+
+/// Layers in drawing order. Group layers are flattened into their children.
+#[derive(Clone, Debug)]
+pub enum TmxLayer {
+    Tiles(TmxTileLayer),
+    Objects(TmxObjectGroup),
+}
+
+#[derive(Clone, Debug)]
+pub struct TmxMap {
+    pub tile_width: i32,
+    pub tile_height: i32,
+    pub infinite: bool,
+    pub tilesets: Vec<TmxTileset>, // sorted by first_gid
+    pub layers: Vec<TmxLayer>,
+    pub properties: HashMap<String, String>,
+}
+
+impl TmxMap {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let xml = std::fs::read_to_string(path).with_context(|| format!("reading map {}", path))?;
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        Self::parse(&xml, dir).with_context(|| format!("parsing map {}", path))
+    }
+
+    /// `dir` is where the map lives; image and tileset paths are relative to it
+    pub fn parse(xml: &str, dir: &Path) -> anyhow::Result<Self> {
+        let doc = roxmltree::Document::parse(xml)?;
+        let root = doc.root_element();
+        if root.tag_name().name() != "map" {
+            bail!("expected <map>, found <{}>", root.tag_name().name());
+        }
+        if attr_or(root, "orientation", "orthogonal") != "orthogonal" {
+            bail!("only orthogonal maps are supported");
+        }
+
+        let mut map = Self {
+            tile_width: attr(root, "tilewidth")?,
+            tile_height: attr(root, "tileheight")?,
+            infinite: attr_or(root, "infinite", "0") == "1",
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            properties: properties(root),
+        };
+
+        for node in root.children().filter(|n| n.is_element()) {
+            match node.tag_name().name() {
+                "tileset" => map.tilesets.push(parse_tileset(node, dir)?),
+                "layer" | "objectgroup" | "group" => parse_layer(node, &Inherited::default(), map.infinite, &mut map.layers)?,
+                _ => {},
+            }
+        }
+        map.tilesets.sort_by_key(|t| t.first_gid);
+
+        Ok(map)
+    }
+
+    /// The tileset a gid belongs to
+    pub fn tileset_for(&self, gid: Gid) -> Option<(usize, &TmxTileset)> {
+        if gid.is_empty() {
+            return None;
+        }
+        // last tileset whose firstgid is not past the id
+        let index = self.tilesets.partition_point(|t| t.first_gid <= gid.id()).checked_sub(1)?;
+        let tileset = &self.tilesets[index];
+        tileset.contains(gid).then_some((index, tileset))
+    }
+
+    pub fn tile_layers(&self) -> impl Iterator<Item = &TmxTileLayer> {
+        self.layers.iter().filter_map(|layer| match layer {
+            TmxLayer::Tiles(tiles) => Some(tiles),
+            TmxLayer::Objects(_) => None,
+        })
+    }
+
+    pub fn object_groups(&self) -> impl Iterator<Item = &TmxObjectGroup> {
+        self.layers.iter().filter_map(|layer| match layer {
+            TmxLayer::Objects(group) => Some(group),
+            TmxLayer::Tiles(_) => None,
+        })
+    }
+
+    /// Smallest rect of tiles holding every non-empty cell, as (x, y, w, h) in tiles
+    pub fn used_bounds(&self) -> Option<(i32, i32, i32, i32)> {
+        let mut bounds: Option<(i32, i32, i32, i32)> = None;
+        for layer in self.tile_layers() {
+            for chunk in &layer.chunks {
+                for (i, gid) in chunk.tiles.iter().enumerate() {
+                    if gid.is_empty() {
+                        continue;
+                    }
+                    let x = chunk.x + i as i32 % chunk.width;
+                    let y = chunk.y + i as i32 / chunk.width;
+                    bounds = Some(match bounds {
+                        None => (x, y, x, y),
+                        Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                    });
+                }
+            }
+        }
+        bounds.map(|(x0, y0, x1, y1)| (x0, y0, x1 - x0 + 1, y1 - y0 + 1))
+    }
+}
+
+// visibility, opacity and offset that group layers pass down to their children
+#[derive(Clone)]
+struct Inherited {
+    visible: bool,
+    opacity: f32,
+    offset_x: f32,
+    offset_y: f32,
+}
+
+impl Default for Inherited {
+    fn default() -> Self {
+        Self { visible: true, opacity: 1.0, offset_x: 0.0, offset_y: 0.0 }
+    }
+}
+
+impl Inherited {
+    fn apply(&self, node: Node) -> anyhow::Result<Inherited> {
+        Ok(Inherited {
+            visible: self.visible && attr_or(node, "visible", "1") != "0",
+            opacity: self.opacity * attr_or(node, "opacity", "1").parse::<f32>()?,
+            offset_x: self.offset_x + attr_or(node, "offsetx", "0").parse::<f32>()?,
+            offset_y: self.offset_y + attr_or(node, "offsety", "0").parse::<f32>()?,
+        })
+    }
+}
+
+fn parse_layer(node: Node, parent: &Inherited, infinite: bool, layers: &mut Vec<TmxLayer>) -> anyhow::Result<()> {
+    let inherited = parent.apply(node)?;
+    let id = attr_or(node, "id", "0").parse()?;
+    let name = attr_or(node, "name", "").to_string();
+
+    match node.tag_name().name() {
+        "group" => {
+            for child in node.children().filter(|n| n.is_element()) {
+                if matches!(child.tag_name().name(), "layer" | "objectgroup" | "group") {
+                    parse_layer(child, &inherited, infinite, layers)?;
+                }
+            }
+        },
+        "layer" => {
+            let data = child(node, "data").ok_or_else(|| anyhow!("layer '{}' has no <data>", name))?;
+            let chunks = if infinite {
+                data.children()
+                    .filter(|n| n.has_tag_name("chunk"))
+                    .map(|chunk| parse_chunk(chunk, data, attr(chunk, "x")?, attr(chunk, "y")?))
+                    .collect::<anyhow::Result<Vec<_>>>()?
+            } else {
+                vec![parse_chunk(node, data, 0, 0)?]
+            };
+
+            layers.push(TmxLayer::Tiles(TmxTileLayer {
+                id,
+                name,
+                visible: inherited.visible,
+                opacity: inherited.opacity,
+                offset_x: inherited.offset_x,
+                offset_y: inherited.offset_y,
+                chunks,
+                properties: properties(node),
+            }));
+        },
+        "objectgroup" => {
+            let objects = node.children()
+                .filter(|n| n.has_tag_name("object"))
+                .map(parse_object)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            layers.push(TmxLayer::Objects(TmxObjectGroup {
+                id,
+                name,
+                visible: inherited.visible,
+                opacity: inherited.opacity,
+                offset_x: inherited.offset_x,
+                offset_y: inherited.offset_y,
+                objects,
+                properties: properties(node),
+            }));
+        },
+        _ => {},
+    }
+
+    Ok(())
+}
+
+// `sized` carries width/height: the <chunk> itself, or the <layer> for finite maps
+fn parse_chunk(sized: Node, data: Node, x: i32, y: i32) -> anyhow::Result<TmxChunk> {
+    let width: i32 = attr(sized, "width")?;
+    let height: i32 = attr(sized, "height")?;
+
+    let tiles: Vec<Gid> = match data.attribute("encoding") {
+        Some("csv") => {
+            let text = if sized.has_tag_name("chunk") { sized.text() } else { data.text() };
+            text.unwrap_or("")
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<u32>().map(Gid).with_context(|| format!("bad gid '{}'", v)))
+                .collect::<anyhow::Result<_>>()?
+        },
+        None => {
+            // plain xml, one <tile gid=".."/> per cell
+            let parent = if sized.has_tag_name("chunk") { sized } else { data };
+            parent.children()
+                .filter(|n| n.has_tag_name("tile"))
+                .map(|n| Ok(Gid(attr_or(n, "gid", "0").parse()?)))
+                .collect::<anyhow::Result<_>>()?
+        },
+        Some(other) => bail!("{} encoded layers aren't supported, save the map as CSV", other),
+    };
+
+    if tiles.len() != (width * height) as usize {
+        bail!("chunk at ({}, {}) should have {} tiles but has {}", x, y, width * height, tiles.len());
+    }
+
+    Ok(TmxChunk { x, y, width, height, tiles })
+}
+
+fn parse_object(node: Node) -> anyhow::Result<TmxObject> {
+    // Tiled 1.9 renamed "type" to "class"
+    let class = node.attribute("class").or(node.attribute("type")).unwrap_or("").to_string();
+
+    Ok(TmxObject {
+        id: attr_or(node, "id", "0").parse()?,
+        name: attr_or(node, "name", "").to_string(),
+        class,
+        x: attr_or(node, "x", "0").parse()?,
+        y: attr_or(node, "y", "0").parse()?,
+        width: attr_or(node, "width", "0").parse()?,
+        height: attr_or(node, "height", "0").parse()?,
+        gid: node.attribute("gid").map(|g| g.parse().map(Gid)).transpose()?,
+        polygon: child(node, "polygon").map(|p| parse_points(attr_or(p, "points", ""))).transpose()?.unwrap_or_default(),
+        visible: attr_or(node, "visible", "1") != "0",
+        properties: properties(node),
+    })
+}
+
+// "0,0 16,0 16,8": x,y pairs split by spaces
+fn parse_points(points: &str) -> anyhow::Result<Vec<(f32, f32)>> {
+    points.split_whitespace()
+        .map(|point| {
+            let (x, y) = point.split_once(',').ok_or_else(|| anyhow!("bad point '{}'", point))?;
+            Ok((x.parse()?, y.parse()?))
+        })
+        .collect()
+}
+
+fn parse_tileset(node: Node, dir: &Path) -> anyhow::Result<TmxTileset> {
+    let first_gid = attr(node, "firstgid")?;
+
+    // external tileset: the rest lives in a .tsx next to the map
+    if let Some(source) = node.attribute("source") {
+        let path = dir.join(source);
+        let xml = std::fs::read_to_string(&path).with_context(|| format!("reading tileset {}", path.display()))?;
+        let doc = roxmltree::Document::parse(&xml).with_context(|| format!("parsing tileset {}", path.display()))?;
+        let tsx_dir = path.parent().unwrap_or(Path::new(""));
+        return parse_tileset_body(doc.root_element(), first_gid, tsx_dir);
+    }
+
+    parse_tileset_body(node, first_gid, dir)
+}
+
+fn parse_tileset_body(node: Node, first_gid: u32, dir: &Path) -> anyhow::Result<TmxTileset> {
+    let name = attr_or(node, "name", "").to_string();
+    let image = child(node, "image").ok_or_else(|| anyhow!("tileset '{}' has no image (image collections aren't supported)", name))?;
+
+    let mut animations = HashMap::new();
+    let mut tile_properties = HashMap::new();
+    let mut tile_shapes = HashMap::new();
+    for tile in node.children().filter(|n| n.has_tag_name("tile")) {
+        let id: u32 = attr(tile, "id")?;
+
+        if let Some(animation) = child(tile, "animation") {
+            let frames = animation.children()
+                .filter(|n| n.has_tag_name("frame"))
+                .map(|f| Ok(TmxFrame { tile_id: attr(f, "tileid")?, duration_ms: attr(f, "duration")? }))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            animations.insert(id, frames);
+        }
+
+        let props = properties(tile);
+        if !props.is_empty() {
+            tile_properties.insert(id, props);
+        }
+
+        if let Some(group) = child(tile, "objectgroup") {
+            let shapes = group.children()
+                .filter(|n| n.has_tag_name("object"))
+                .map(parse_object)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            tile_shapes.insert(id, shapes);
+        }
+    }
+
+    Ok(TmxTileset {
+        first_gid,
+        name,
+        tile_width: attr(node, "tilewidth")?,
+        tile_height: attr(node, "tileheight")?,
+        tile_count: attr(node, "tilecount")?,
+        columns: attr(node, "columns")?,
+        spacing: attr_or(node, "spacing", "0").parse()?,
+        margin: attr_or(node, "margin", "0").parse()?,
+        image: dir.join(attr_or(image, "source", "")).to_string_lossy().into_owned(),
+        image_width: attr(image, "width")?,
+        image_height: attr(image, "height")?,
+        animations,
+        tile_properties,
+        tile_shapes,
+    })
+}
+
+/// Layer name patterns from config: exact, or a prefix when they end in '*'
+/// ("Objects*" covers Objects1 to Objects4)
+pub fn name_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+/// A bool custom property, None when it isn't set
+pub fn flag(properties: &HashMap<String, String>, name: &str) -> Option<bool> {
+    properties.get(name).map(|value| value == "true")
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn attr<T: std::str::FromStr>(node: Node, name: &str) -> anyhow::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value = node.attribute(name)
+        .ok_or_else(|| anyhow!("<{}> is missing '{}'", node.tag_name().name(), name))?;
+    value.parse().with_context(|| format!("<{}> has a bad '{}': {}", node.tag_name().name(), name, value))
+}
+
+fn attr_or<'a>(node: Node<'a, '_>, name: &str, default: &'a str) -> &'a str {
+    node.attribute(name).unwrap_or(default)
+}
+
+fn properties(node: Node) -> HashMap<String, String> {
+    let Some(props) = child(node, "properties") else { return HashMap::new() };
+
+    props.children()
+        .filter(|n| n.has_tag_name("property"))
+        .filter_map(|p| {
+            let name = p.attribute("name")?.to_string();
+            // multi-line strings keep their value in the text instead
+            let value = p.attribute("value").or(p.text()).unwrap_or("").to_string();
+            Some((name, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(tilesets: &str, layers: &str, infinite: bool) -> TmxMap {
+        let xml = format!(
+            r#"<map orientation="orthogonal" tilewidth="16" tileheight="16" infinite="{}">{}{}</map>"#,
+            if infinite { 1 } else { 0 }, tilesets, layers,
+        );
+        TmxMap::parse(&xml, Path::new("maps")).unwrap()
+    }
+
+    fn tileset(first_gid: u32, name: &str, count: u32) -> String {
+        format!(
+            r#"<tileset firstgid="{}" name="{}" tilewidth="16" tileheight="16" tilecount="{}" columns="4">
+                <image source="{}.png" width="64" height="64"/>
+            </tileset>"#,
+            first_gid, name, count, name,
+        )
+    }
+
+    fn gid_at(layer: &TmxTileLayer, x: i32, y: i32) -> Option<Gid> {
+        layer.chunks.iter()
+            .find(|c| x >= c.x && x < c.x + c.width && y >= c.y && y < c.y + c.height)
+            .map(|c| c.tiles[((y - c.y) * c.width + (x - c.x)) as usize])
+    }
+
+    #[test]
+    fn finite_layers_are_one_chunk_at_the_origin() {
+        let layer = r#"<layer id="1" name="Ground" width="3" height="2"><data encoding="csv">
+            1,2,3,
+            4,0,6
+        </data></layer>"#;
+        let map = map("", layer, false);
+
+        let ground = map.tile_layers().next().unwrap();
+        let [chunk] = &ground.chunks[..] else { panic!("expected one chunk") };
+        assert_eq!((chunk.x, chunk.y, chunk.width, chunk.height), (0, 0, 3, 2));
+        assert_eq!(gid_at(ground, 2, 0), Some(Gid(3)));
+        assert_eq!(gid_at(ground, 0, 1), Some(Gid(4)));
+        assert!(gid_at(ground, 1, 1).unwrap().is_empty());
+        assert_eq!(map.used_bounds(), Some((0, 0, 3, 2)));
+    }
+
+    #[test]
+    fn infinite_chunks_land_where_they_say() {
+        let layer = r#"<layer id="1" name="Ground" width="4" height="4"><data encoding="csv">
+            <chunk x="-4" y="0" width="2" height="2">0,0,0,7</chunk>
+            <chunk x="16" y="-2" width="2" height="1">8,0</chunk>
+        </data></layer>"#;
+        let map = map("", layer, true);
+
+        let ground = map.tile_layers().next().unwrap();
+        assert_eq!(ground.chunks.len(), 2);
+        assert_eq!(gid_at(ground, -3, 1), Some(Gid(7)));
+        assert_eq!(gid_at(ground, 16, -2), Some(Gid(8)));
+        assert_eq!(gid_at(ground, 0, 0), None);
+        // from the 7 at (-3, 1) to the 8 at (16, -2)
+        assert_eq!(map.used_bounds(), Some((-3, -2, 20, 4)));
+    }
+
+    #[test]
+    fn plain_xml_data_and_a_short_chunk() {
+        let layer = r#"<layer id="1" name="Ground" width="2" height="1"><data>
+            <tile gid="5"/><tile/>
+        </data></layer>"#;
+        let map = map("", layer, false);
+        let ground = map.tile_layers().next().unwrap();
+        assert_eq!(ground.chunks[0].tiles, vec![Gid(5), Gid(0)]);
+
+        let short = r#"<map orientation="orthogonal" tilewidth="16" tileheight="16">
+            <layer id="1" name="Ground" width="2" height="2"><data encoding="csv">1,2,3</data></layer>
+        </map>"#;
+        assert!(TmxMap::parse(short, Path::new("")).is_err());
+    }
+
+    #[test]
+    fn each_flip_flag_is_read_without_changing_the_tile() {
+        let tile = 37;
+        let cases = [
+            (FLIPPED_HORIZONTALLY, (true, false, false)),
+            (FLIPPED_VERTICALLY, (false, true, false)),
+            (FLIPPED_DIAGONALLY, (false, false, true)),
+            (FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY, (true, true, true)),
+            (ROTATED_HEXAGONAL_120, (false, false, false)),
+            (0, (false, false, false)),
+        ];
+
+        for (flags, expected) in cases {
+            // as it appears in a CSV layer
+            let layer = format!(r#"<layer id="1" name="Ground" width="1" height="1"><data encoding="csv">{}</data></layer>"#, tile | flags);
+            let map = map("", &layer, false);
+            let gid = map.tile_layers().next().unwrap().chunks[0].tiles[0];
+
+            assert_eq!(gid.id(), tile, "flags {:#x}", flags);
+            assert_eq!((gid.flipped_horizontally(), gid.flipped_vertically(), gid.flipped_diagonally()), expected, "flags {:#x}", flags);
+        }
+        assert!(Gid(FLIPPED_HORIZONTALLY).is_empty());
+    }
+
+    #[test]
+    fn tileset_for_finds_the_tileset_a_gid_falls_in() {
+        // out of order on purpose, and with a gap between 5..=20 and 31..
+        let tilesets = tileset(31, "trees", 8) + &tileset(1, "floor", 4) + &tileset(5, "walls", 16);
+        let map = map(&tilesets, "", false);
+        assert_eq!(map.tilesets.iter().map(|t| t.first_gid).collect::<Vec<_>>(), [1, 5, 31]);
+        assert_eq!(map.tilesets[0].image, Path::new("maps").join("floor.png").to_string_lossy());
+
+        let name = |gid: u32| map.tileset_for(Gid(gid)).map(|(_, tileset)| tileset.name.as_str());
+        assert_eq!(name(0), None);
+        assert_eq!(name(1), Some("floor"));
+        assert_eq!(name(4), Some("floor"));
+        assert_eq!(name(5), Some("walls"));
+        assert_eq!(name(20), Some("walls"));
+        assert_eq!(name(21), None);
+        assert_eq!(name(30), None);
+        assert_eq!(name(31), Some("trees"));
+        assert_eq!(name(38), Some("trees"));
+        assert_eq!(name(39), None);
+        // flags don't change which tileset
+        assert_eq!(name(31 | FLIPPED_HORIZONTALLY), Some("trees"));
+        assert_eq!(map.tileset_for(Gid(6)).map(|(index, _)| index), Some(1));
+    }
+}
@@ -24,9 +24,13 @@ impl Percent {
 
     pub fn as_f64(&self) -> f64 { self.0 }
 
+    /// This much of the value, e.g. 10% scaled by 0.5 is 5%
+    pub fn scaled(&self, factor: f64) -> Percent { Percent(self.0 * factor) }
+
     pub fn increase(&mut self, value: Percent) {
-        if self.0 + value.0 > 100.0 {
-            self.0 = 100.0
+        // stored as a fraction, so 100% is 1.0
+        if self.0 + value.0 > 1.0 {
+            self.0 = 1.0
         } else {
             self.0 += value.0
         }
@@ -48,6 +52,7 @@ impl Clone for Percent {
 }
 
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Gender {
     Girl,
     Boy,
@@ -67,4 +72,34 @@ impl Food {
             nutritional_value: nutritional_value,
         }
     } 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn increase_stops_at_a_hundred_percent() {
+        let mut level = Percent::new(90.0);
+        level.increase(Percent::new(5.0));
+        assert!((level.as_f64() - 0.95).abs() < 1e-9);
+
+        level.increase(Percent::new(15.0));
+        assert_eq!(level.as_f64(), 1.0);
+    }
+
+    #[test]
+    fn feeding_a_full_dog_doesnt_overfill_it() {
+        let mut food_level = Percent::new(95.0);
+        let kibble = Food::new("kibble".to_string(), Percent::new(15.0));
+        food_level.increase(kibble.nutritional_value.clone());
+        assert_eq!(food_level.as_f64(), 1.0);
+    }
+
+    #[test]
+    fn decrease_stops_at_zero() {
+        let mut level = Percent::new(10.0);
+        level.decrease(Percent::new(25.0));
+        assert_eq!(level.as_f64(), 0.0);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::draw::DrawList;
+use crate::rng::Rng;
+use chrono::Datelike;
+use raylib::prelude::Vector2;
+use raylib::color::Color;
+
+// most drops/flakes on screen at once, at full strength
+const MAX_DROPS: usize = 300;
+// seconds between thunder claps in a storm
+const THUNDER_MIN: f32 = 6.0;
+const THUNDER_MAX: f32 = 20.0;
+// how quickly a lightning flash fades, per second
+const FLASH_FADE: f32 = 3.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Weather {
+    Sunny,
+    Rain,
+    Snow,
+    HeatWave,
+    Thunderstorm,
+}
+
+// (weather, weight) for each season
+const WINTER: [(Weather, u32); 4] = [(Weather::Sunny, 4), (Weather::Rain, 2), (Weather::Snow, 4), (Weather::Thunderstorm, 1)];
+const SUMMER: [(Weather, u32); 4] = [(Weather::Sunny, 6), (Weather::Rain, 1), (Weather::HeatWave, 3), (Weather::Thunderstorm, 2)];
+const SPRING_AUTUMN: [(Weather, u32); 3] = [(Weather::Sunny, 5), (Weather::Rain, 4), (Weather::Thunderstorm, 1)];
+
+impl Weather {
+    /// The weather on `date`. The same seed and date always give the same
+    /// weather, so the forecast is never wrong.
+    pub fn for_day(seed: u64, date: chrono::NaiveDate) -> Self {
+        let mut rng = Rng::new(seed ^ (date.num_days_from_ce() as u64).wrapping_mul(0x2545_f491_4f6c_dd1d));
+        let table: &[(Weather, u32)] = match date.month() {
+            12 | 1 | 2 => &WINTER,
+            6..=8 => &SUMMER,
+            _ => &SPRING_AUTUMN,
+        };
+
+        let total: u32 = table.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rng.range(0, total as i64) as u32;
+        for (weather, weight) in table {
+            if roll < *weight {
+                return *weather;
+            }
+            roll -= weight;
+        }
+        Weather::Sunny
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weather::Sunny => "sunny",
+            Weather::Rain => "rain",
+            Weather::Snow => "snow",
+            Weather::HeatWave => "heat wave",
+            Weather::Thunderstorm => "thunderstorm",
+        }
+    }
+
+    /// How much faster a dog gets thirsty. The house keeps some of the heat out.
+    pub fn thirst(&self, outdoors: bool) -> f64 {
+        match (self, outdoors) {
+            (Weather::HeatWave, true) => 3.0,
+            (Weather::HeatWave, false) => 1.5,
+            _ => 1.0,
+        }
+    }
+
+    /// How wet the ground is where the dog is, 0 to 1. Wet ground means a muddy dog.
+    pub fn wetness(&self, outdoors: bool) -> f32 {
+        if !outdoors {
+            return 0.0;
+        }
+        match self {
+            Weather::Rain => 0.6,
+            Weather::Thunderstorm => 1.0,
+            // melting on the fur
+            Weather::Snow => 0.2,
+            Weather::Sunny | Weather::HeatWave => 0.0,
+        }
+    }
+}
+
+// a raindrop or snowflake, in screen pixels
+struct Drop {
+    position: Vector2,
+    velocity: Vector2,
+    size: f32,
+}
+
+/// What the weather looks like: falling rain and snow, heat haze and
+/// lightning, drawn over the world in screen space
+pub struct WeatherOverlay {
+    weather: Weather,
+    drops: Vec<Drop>,
+    rng: Rng,
+    // seconds until the next thunder clap
+    thunder_in: f32,
+    // lightning brightness, 0 to 1
+    flash: f32,
+    // seconds, for the heat shimmer
+    time: f32,
+}
+
+impl WeatherOverlay {
+    pub fn new(weather: Weather, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let thunder_in = rng.range_f32(THUNDER_MIN, THUNDER_MAX);
+        Self { weather, drops: Vec::new(), rng, thunder_in, flash: 0.0, time: 0.0 }
+    }
+
+    /// Rain left over from yesterday stops; new drops fall the new way
+    pub fn set_weather(&mut self, weather: Weather) {
+        if weather != self.weather {
+            self.weather = weather;
+            self.drops.clear();
+        }
+    }
+
+    /// Moves everything along by dt seconds. Returns true on the tick
+    /// thunder cracks, wherever the dog is.
+    pub fn update(&mut self, dt: f32, screen_w: f32, screen_h: f32) -> bool {
+        self.time += dt;
+        self.flash = (self.flash - FLASH_FADE * dt).max(0.0);
+
+        let wanted = match self.weather {
+            Weather::Rain => MAX_DROPS / 2,
+            Weather::Thunderstorm => MAX_DROPS,
+            Weather::Snow => MAX_DROPS / 3,
+            Weather::Sunny | Weather::HeatWave => 0,
+        };
+
+        // top up a few a tick, so a shower starts gradually
+        for _ in 0..4 {
+            if self.drops.len() >= wanted {
+                break;
+            }
+            // anywhere above the screen, so they don't arrive in a line
+            let y = -self.rng.range_f32(0.0, screen_h);
+            let drop = self.spawn_drop(screen_w, y);
+            self.drops.push(drop);
+        }
+
+        for i in 0..self.drops.len() {
+            let drop = &mut self.drops[i];
+            drop.position.x += drop.velocity.x * dt;
+            drop.position.y += drop.velocity.y * dt;
+            if drop.position.y > screen_h || drop.position.x < -16.0 || drop.position.x > screen_w + 16.0 {
+                self.drops[i] = self.spawn_drop(screen_w, -8.0);
+            }
+        }
+        self.drops.truncate(wanted);
+
+        if self.weather != Weather::Thunderstorm {
+            return false;
+        }
+        self.thunder_in -= dt;
+        if self.thunder_in > 0.0 {
+            return false;
+        }
+        self.thunder_in = self.rng.range_f32(THUNDER_MIN, THUNDER_MAX);
+        self.flash = 1.0;
+        true
+    }
+
+    fn spawn_drop(&mut self, screen_w: f32, y: f32) -> Drop {
+        let x = self.rng.range_f32(-16.0, screen_w + 16.0);
+        match self.weather {
+            Weather::Snow => Drop {
+                position: Vector2::new(x, y),
+                velocity: Vector2::new(self.rng.range_f32(-12.0, 12.0), self.rng.range_f32(18.0, 36.0)),
+                size: self.rng.range_f32(0.8, 1.8),
+            },
+            _ => Drop {
+                position: Vector2::new(x, y),
+                velocity: Vector2::new(-40.0, self.rng.range_f32(260.0, 340.0)),
+                size: self.rng.range_f32(4.0, 8.0),
+            },
+        }
+    }
+
+    /// The rain, snow or haze. Only outdoors: the house has a roof.
+    pub fn draw(&self, d: &mut DrawList, screen_w: f32, screen_h: f32) {
+        match self.weather {
+            Weather::Rain | Weather::Thunderstorm => {
+                let color = Color::new(170, 190, 230, 150);
+                for drop in &self.drops {
+                    // a streak along the way it's falling
+                    let speed = drop.velocity.length().max(1.0);
+                    let tail = Vector2::new(
+                        drop.position.x - drop.velocity.x / speed * drop.size,
+                        drop.position.y - drop.velocity.y / speed * drop.size,
+                    );
+                    d.draw_line_v(tail, drop.position, color);
+                }
+            },
+            Weather::Snow => {
+                for drop in &self.drops {
+                    d.draw_circle_v(drop.position, drop.size, Color::WHITE.fade(0.85));
+                }
+            },
+            Weather::HeatWave => {
+                // a warm haze that breathes a little
+                let alpha = 0.12 + 0.04 * (self.time * 1.5).sin();
+                d.draw_rectangle(0, 0, screen_w as i32, screen_h as i32, Color::new(255, 170, 60, 255).fade(alpha));
+            },
+            Weather::Sunny => {},
+        }
+    }
+
+    /// Lightning lights up the screen, a bit through the windows indoors
+    pub fn draw_flash(&self, d: &mut DrawList, screen_w: f32, screen_h: f32, outdoors: bool) {
+        if self.flash <= 0.0 {
+            return;
+        }
+        let strength = if outdoors { 0.7 } else { 0.25 };
+        d.draw_rectangle(0, 0, screen_w as i32, screen_h as i32, Color::WHITE.fade(self.flash * strength));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    // every day of the month, for a spread of seeds
+    fn month_of_weather(month: u32) -> Vec<Weather> {
+        (0..50u64).flat_map(|seed| (1..=28).map(move |day| Weather::for_day(seed, date(month, day)))).collect()
+    }
+
+    #[test]
+    fn the_same_seed_and_date_give_the_same_weather() {
+        for day in 1..=28 {
+            assert_eq!(Weather::for_day(7, date(3, day)), Weather::for_day(7, date(3, day)));
+        }
+
+        // but it does change from day to day and game to game
+        let march: Vec<Weather> = (1..=28).map(|day| Weather::for_day(7, date(3, day))).collect();
+        assert!(march.iter().any(|weather| *weather != march[0]));
+        let other_game: Vec<Weather> = (1..=28).map(|day| Weather::for_day(8, date(3, day))).collect();
+        assert_ne!(march, other_game);
+    }
+
+    #[test]
+    fn each_season_draws_from_its_own_table() {
+        for (months, table) in [(&[12, 1, 2], &WINTER[..]), (&[6, 7, 8], &SUMMER[..]), (&[3, 4, 5], &SPRING_AUTUMN[..]), (&[9, 10, 11], &SPRING_AUTUMN[..])] {
+            for &month in months {
+                let weathers = month_of_weather(month);
+                for (weather, _) in table {
+                    assert!(weathers.contains(weather), "no {:?} in month {}", weather, month);
+                }
+                for weather in weathers {
+                    assert!(table.iter().any(|(w, _)| *w == weather), "{:?} in month {}", weather, month);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weights_set_how_often_each_comes_up() {
+        // sunny is 6 in 12 in summer, rain 1 in 12
+        let summer = month_of_weather(7);
+        let share = |weather| summer.iter().filter(|w| **w == weather).count() as f32 / summer.len() as f32;
+        assert!((share(Weather::Sunny) - 0.5).abs() < 0.05, "{}", share(Weather::Sunny));
+        assert!((share(Weather::Rain) - 1.0 / 12.0).abs() < 0.03, "{}", share(Weather::Rain));
+    }
+}